@@ -0,0 +1,89 @@
+//! Rendering throughput, on the records of `benchmark_logs.txt`.
+//!
+//! Allocations are counted as well: the number of allocations per record is printed for
+//! `LogRecord::format` (a new `String` per record) and `LogRecord::format_into` (a buffer
+//! reused across records).
+use bunyan::{Format, FormatOptions, LogRecord, TimeMode};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations_per_record(records: &[LogRecord], mut render: impl FnMut(&LogRecord)) -> f64 {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for record in records {
+        render(record);
+    }
+    (ALLOCATIONS.load(Ordering::Relaxed) - before) as f64 / records.len() as f64
+}
+
+fn format(c: &mut Criterion) {
+    let input = std::fs::read_to_string(
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("benchmark_logs.txt"),
+    )
+    .unwrap();
+    let records: Vec<LogRecord> = input
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+
+    for (name, format) in [("long", Format::Long), ("short", Format::Short)] {
+        let options = FormatOptions {
+            format,
+            time: TimeMode::Utc,
+            ..FormatOptions::default()
+        };
+
+        let allocating = allocations_per_record(&records, |record| {
+            black_box(record.format(&options).unwrap());
+        });
+        let mut buffer = String::new();
+        let reusing = allocations_per_record(&records, |record| {
+            buffer.clear();
+            record.format_into(&mut buffer, &options).unwrap();
+            black_box(&buffer);
+        });
+        println!(
+            "{}: {:.1} allocations per record with `format`, {:.1} with `format_into`",
+            name, allocating, reusing
+        );
+
+        c.bench_function(&format!("format/{}", name), |b| {
+            b.iter(|| {
+                for record in &records {
+                    black_box(record.format(&options).unwrap());
+                }
+            })
+        });
+        c.bench_function(&format!("format_into/{}", name), |b| {
+            let mut buffer = String::new();
+            b.iter(|| {
+                for record in &records {
+                    buffer.clear();
+                    record.format_into(&mut buffer, &options).unwrap();
+                    black_box(&buffer);
+                }
+            })
+        });
+    }
+}
+
+criterion_group!(benches, format);
+criterion_main!(benches);
@@ -0,0 +1,377 @@
+use crate::record::LogRecord;
+use crate::NAMED_LOG_LEVELS;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+/// A filtering condition on log records, e.g. `this.level >= 40 && this.component == "db"`.
+///
+/// It is a (very) small subset of the JavaScript expressions accepted by node-bunyan's
+/// `-c/--condition` flag:
+/// - fields: `level`, `pid`, `name`, `hostname`, `msg`, `time`, `v` and any extra field, with an
+///   optional `this.` prefix. Nested fields can be accessed using dots (e.g. `this.req.method`);
+/// - literals: numbers, single or double quoted strings, `true`, `false`, `null` and the named
+///   levels (`TRACE`, `DEBUG`, `INFO`, `WARN`, `ERROR`, `FATAL`);
+/// - operators: `==`, `!=`, `<`, `<=`, `>`, `>=`, `&&`, `||` and parentheses.
+///
+/// Fields that are missing from a record evaluate to `undefined`: equality comparisons against
+/// them are false, as are ordering comparisons.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Condition(Expr);
+
+impl Condition {
+    /// Evaluate the condition against a log record.
+    pub(crate) fn matches(&self, record: &LogRecord) -> bool {
+        self.0.evaluate(record).is_truthy()
+    }
+}
+
+impl FromStr for Condition {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(s)?;
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+        };
+        let expr = parser.parse_or()?;
+        match parser.peek() {
+            None => Ok(Condition(expr)),
+            Some(token) => Err(anyhow::anyhow!(format!(
+                "Invalid condition: unexpected '{}' in '{}'",
+                token, s
+            ))),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Expr {
+    Field(Vec<String>),
+    Literal(Value),
+    Compare(Box<Expr>, CompareOp, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// The result of evaluating an expression: `None` stands for `undefined`.
+struct Evaluated<'a>(Option<Cow<'a, Value>>);
+
+impl Evaluated<'_> {
+    fn from_bool(b: bool) -> Self {
+        Evaluated(Some(Cow::Owned(Value::Bool(b))))
+    }
+
+    fn is_truthy(&self) -> bool {
+        match self.0.as_deref() {
+            None | Some(Value::Null) => false,
+            Some(Value::Bool(b)) => *b,
+            Some(Value::Number(n)) => n.as_f64().is_some_and(|n| n != 0.0),
+            Some(Value::String(s)) => !s.is_empty(),
+            Some(Value::Array(_)) | Some(Value::Object(_)) => true,
+        }
+    }
+}
+
+impl Expr {
+    fn evaluate<'a>(&'a self, record: &'a LogRecord) -> Evaluated<'a> {
+        match self {
+            Expr::Field(path) => Evaluated(lookup(record, path)),
+            Expr::Literal(value) => Evaluated(Some(Cow::Borrowed(value))),
+            Expr::And(lhs, rhs) => Evaluated::from_bool(
+                lhs.evaluate(record).is_truthy() && rhs.evaluate(record).is_truthy(),
+            ),
+            Expr::Or(lhs, rhs) => Evaluated::from_bool(
+                lhs.evaluate(record).is_truthy() || rhs.evaluate(record).is_truthy(),
+            ),
+            Expr::Compare(lhs, op, rhs) => {
+                let lhs = lhs.evaluate(record).0;
+                let rhs = rhs.evaluate(record).0;
+                Evaluated::from_bool(compare(lhs.as_deref(), *op, rhs.as_deref()))
+            }
+        }
+    }
+}
+
+fn lookup<'a>(record: &'a LogRecord, path: &[String]) -> Option<Cow<'a, Value>> {
+    let (first, rest) = path.split_first()?;
+    let value = match first.as_str() {
+        "level" => Value::from(record.level),
+        "pid" => Value::from(record.pid?),
+        "name" => Value::from(record.name.as_deref()?),
+        "hostname" => Value::from(record.hostname.as_deref()?),
+        "msg" => Value::from(record.message.as_ref()),
+        "time" => Value::from(record.time.to_rfc3339()),
+        "v" => Value::from(record.v?),
+        key => {
+            let value = rest
+                .iter()
+                .try_fold(record.extras.get(key)?, |value, key| {
+                    value.get(key.as_str())
+                })?;
+            return Some(Cow::Borrowed(value));
+        }
+    };
+    // Core fields have no nested fields.
+    if rest.is_empty() {
+        Some(Cow::Owned(value))
+    } else {
+        None
+    }
+}
+
+fn compare(lhs: Option<&Value>, op: CompareOp, rhs: Option<&Value>) -> bool {
+    let (lhs, rhs) = match (lhs, rhs) {
+        (Some(lhs), Some(rhs)) => (lhs, rhs),
+        // `undefined` is neither equal, nor ordered with respect to anything.
+        _ => return op == CompareOp::Ne,
+    };
+    let ordering = match (lhs, rhs) {
+        (Value::String(l), Value::String(r)) => Some(l.cmp(r)),
+        (Value::Number(_), _) | (_, Value::Number(_)) => match (to_number(lhs), to_number(rhs)) {
+            (Some(l), Some(r)) => l.partial_cmp(&r),
+            _ => None,
+        },
+        _ => {
+            return match op {
+                CompareOp::Eq => lhs == rhs,
+                CompareOp::Ne => lhs != rhs,
+                _ => false,
+            }
+        }
+    };
+    match (op, ordering) {
+        (CompareOp::Ne, ordering) => ordering != Some(Ordering::Equal),
+        (_, None) => false,
+        (CompareOp::Eq, Some(o)) => o == Ordering::Equal,
+        (CompareOp::Lt, Some(o)) => o == Ordering::Less,
+        (CompareOp::Le, Some(o)) => o != Ordering::Greater,
+        (CompareOp::Gt, Some(o)) => o == Ordering::Greater,
+        (CompareOp::Ge, Some(o)) => o != Ordering::Less,
+    }
+}
+
+/// Numeric coercion, loosely following JavaScript: numeric strings are parsed.
+fn to_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::Bool(b) => Some(if *b { 1.0 } else { 0.0 }),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Identifier(String),
+    Literal(Value),
+    Operator(&'static str),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Identifier(s) => write!(f, "{}", s),
+            Token::Literal(v) => write!(f, "{}", v),
+            Token::Operator(o) => write!(f, "{}", o),
+        }
+    }
+}
+
+const OPERATORS: [&str; 13] = [
+    "===", "!==", "==", "!=", "<=", ">=", "&&", "||", "<", ">", "(", ")", ".",
+];
+
+fn tokenize(s: &str) -> Result<Vec<Token>, anyhow::Error> {
+    let mut tokens = Vec::new();
+    let mut chars = s.char_indices().peekable();
+    while let Some(&(i, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' || c == '\'' {
+            chars.next();
+            let mut literal = String::new();
+            loop {
+                match chars.next() {
+                    Some((_, '\\')) => {
+                        if let Some((_, escaped)) = chars.next() {
+                            literal.push(escaped)
+                        }
+                    }
+                    Some((_, end)) if end == c => {
+                        tokens.push(Token::Literal(Value::String(literal)));
+                        break;
+                    }
+                    Some((_, other)) => literal.push(other),
+                    None => {
+                        return Err(anyhow::anyhow!(format!(
+                            "Invalid condition: unterminated string in '{}'",
+                            s
+                        )))
+                    }
+                }
+            }
+        } else if c.is_ascii_digit() || c == '-' {
+            let end = s[i + 1..]
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '.'))
+                .map_or(s.len(), |end| end + i + 1);
+            let number = s[i..end].parse::<f64>().map_err(|_| {
+                anyhow::anyhow!(format!(
+                    "Invalid condition: invalid number '{}' in '{}'",
+                    &s[i..end],
+                    s
+                ))
+            })?;
+            tokens.push(Token::Literal(Value::from(number)));
+            while chars.peek().is_some_and(|&(j, _)| j < end) {
+                chars.next();
+            }
+        } else if c.is_alphabetic() || c == '_' || c == '$' {
+            let end = s[i..]
+                .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+                .map_or(s.len(), |end| end + i);
+            tokens.push(match &s[i..end] {
+                "true" => Token::Literal(Value::Bool(true)),
+                "false" => Token::Literal(Value::Bool(false)),
+                "null" | "undefined" => Token::Literal(Value::Null),
+                // Named levels are available as constants, e.g. `this.level >= WARN`.
+                identifier => match NAMED_LOG_LEVELS
+                    .iter()
+                    .find(|(name, _)| name.to_uppercase() == identifier)
+                {
+                    Some((_, level)) => Token::Literal(Value::from(*level as u8)),
+                    None => Token::Identifier(identifier.to_owned()),
+                },
+            });
+            while chars.peek().is_some_and(|&(j, _)| j < end) {
+                chars.next();
+            }
+        } else if let Some(operator) = OPERATORS.iter().find(|o| s[i..].starts_with(*o)) {
+            tokens.push(Token::Operator(operator));
+            for _ in 0..operator.len() {
+                chars.next();
+            }
+        } else {
+            return Err(anyhow::anyhow!(format!(
+                "Invalid condition: unexpected '{}' in '{}'",
+                c, s
+            )));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eat(&mut self, operator: &'static str) -> bool {
+        if self.peek() == Some(&Token::Operator(operator)) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, anyhow::Error> {
+        let mut expr = self.parse_and()?;
+        while self.eat("||") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, anyhow::Error> {
+        let mut expr = self.parse_comparison()?;
+        while self.eat("&&") {
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_comparison()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, anyhow::Error> {
+        let lhs = self.parse_operand()?;
+        let op = match self.peek() {
+            // Strict (in)equality is treated as loose (in)equality.
+            Some(Token::Operator("==")) | Some(Token::Operator("===")) => CompareOp::Eq,
+            Some(Token::Operator("!=")) | Some(Token::Operator("!==")) => CompareOp::Ne,
+            Some(Token::Operator("<")) => CompareOp::Lt,
+            Some(Token::Operator("<=")) => CompareOp::Le,
+            Some(Token::Operator(">")) => CompareOp::Gt,
+            Some(Token::Operator(">=")) => CompareOp::Ge,
+            _ => return Ok(lhs),
+        };
+        self.position += 1;
+        let rhs = self.parse_operand()?;
+        Ok(Expr::Compare(Box::new(lhs), op, Box::new(rhs)))
+    }
+
+    fn parse_operand(&mut self) -> Result<Expr, anyhow::Error> {
+        match self.next() {
+            Some(Token::Operator("(")) => {
+                let expr = self.parse_or()?;
+                if self.eat(")") {
+                    Ok(expr)
+                } else {
+                    Err(anyhow::anyhow!("Invalid condition: missing ')'"))
+                }
+            }
+            Some(Token::Literal(value)) => Ok(Expr::Literal(value)),
+            Some(Token::Identifier(identifier)) => {
+                let mut path = vec![identifier];
+                while self.eat(".") {
+                    match self.next() {
+                        Some(Token::Identifier(identifier)) => path.push(identifier),
+                        _ => {
+                            return Err(anyhow::anyhow!(
+                                "Invalid condition: expected a field name after '.'"
+                            ))
+                        }
+                    }
+                }
+                if path[0] == "this" {
+                    path.remove(0);
+                }
+                if path.is_empty() {
+                    Err(anyhow::anyhow!(
+                        "Invalid condition: expected a field name after 'this'"
+                    ))
+                } else {
+                    Ok(Expr::Field(path))
+                }
+            }
+            Some(token) => Err(anyhow::anyhow!(format!(
+                "Invalid condition: unexpected '{}'",
+                token
+            ))),
+            None => Err(anyhow::anyhow!(
+                "Invalid condition: unexpected end of input"
+            )),
+        }
+    }
+}
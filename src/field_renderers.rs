@@ -0,0 +1,153 @@
+use crate::style::parse_color;
+use crate::Paint;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// How an extra field is shown, as decided by a field renderer.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FieldRendering {
+    /// `key=value` after the message, the value painted with `paint` if colors are enabled.
+    Inline { value: String, paint: Paint },
+    /// `key=value` after the message, the value quoted and truncated like any inline extra
+    /// but painted with `paint`.
+    Styled {
+        value: serde_json::Value,
+        paint: Paint,
+    },
+    /// `key: value` in the details below the record: strings as they are, other values as
+    /// indented JSON.
+    Details(serde_json::Value),
+    /// Not shown at all.
+    Hidden,
+}
+
+/// A function rendering the value of an extra field, or returning `None` to leave it to the
+/// generic rendering.
+pub type FieldRenderer = dyn Fn(&serde_json::Value) -> Option<FieldRendering> + Send + Sync;
+
+/// Custom renderings of extra fields, by field name: they take precedence over the generic
+/// rendering of extras, as well as over the special renderings of `err`, `req`, `res`, etc.
+///
+/// The default registry renders `trace_id` and `span_id` dimmed, as they are mostly noise
+/// until they are needed. Start from `FieldRenderers::new` for an empty one.
+///
+/// ```rust
+/// use bunyan::{format_line, FieldRendering, FieldRenderers, FormatOptions, Paint, TimeMode};
+///
+/// let mut field_renderers = FieldRenderers::new();
+/// field_renderers.register("user", |user| {
+///     Some(FieldRendering::Inline {
+///         value: format!("#{}", user.get("id")?),
+///         paint: Paint::default(),
+///     })
+/// });
+/// let options = FormatOptions {
+///     time: TimeMode::Utc,
+///     field_renderers,
+///     ..FormatOptions::default()
+/// };
+/// let line = r#"{"name":"app","pid":1,"hostname":"box","level":30,"msg":"Hi","time":"2012-02-08T22:56:52.856Z","v":0,"user":{"id":7,"name":"Ann"}}"#;
+/// assert_eq!(
+///     format_line(line, &options).unwrap().as_deref(),
+///     Some("[2012-02-08T22:56:52.856Z]  INFO: app/1 on box: Hi (user=#7)\n")
+/// );
+/// ```
+#[derive(Clone)]
+pub struct FieldRenderers {
+    renderers: HashMap<String, Arc<FieldRenderer>>,
+}
+
+impl FieldRenderers {
+    /// A registry without any renderer.
+    pub fn new() -> Self {
+        FieldRenderers {
+            renderers: HashMap::new(),
+        }
+    }
+
+    /// Render the `key` field with `renderer`, replacing its previous renderer, if any.
+    pub fn register<F>(&mut self, key: impl Into<String>, renderer: F)
+    where
+        F: Fn(&serde_json::Value) -> Option<FieldRendering> + Send + Sync + 'static,
+    {
+        self.renderers.insert(key.into(), Arc::new(renderer));
+    }
+
+    /// Render the `key` field with `style`, see `FieldStyle`.
+    pub fn register_style(&mut self, key: impl Into<String>, style: FieldStyle) {
+        self.register(key, move |value| {
+            Some(match style {
+                FieldStyle::Inline(paint) => FieldRendering::Styled {
+                    value: value.clone(),
+                    paint,
+                },
+                FieldStyle::Details => FieldRendering::Details(value.clone()),
+                FieldStyle::Hidden => FieldRendering::Hidden,
+            })
+        });
+    }
+
+    /// The renderer of the `key` field, if any.
+    pub fn get(&self, key: &str) -> Option<&FieldRenderer> {
+        self.renderers.get(key).map(Arc::as_ref)
+    }
+}
+
+impl Default for FieldRenderers {
+    fn default() -> Self {
+        let mut field_renderers = FieldRenderers::new();
+        let dimmed = Paint {
+            dimmed: true,
+            ..Paint::default()
+        };
+        for key in ["trace_id", "span_id"] {
+            field_renderers.register_style(key, FieldStyle::Inline(dimmed));
+        }
+        field_renderers
+    }
+}
+
+impl std::fmt::Debug for FieldRenderers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.renderers.keys()).finish()
+    }
+}
+
+/// A simple rendering of a field, as given on the command line.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FieldStyle {
+    /// Inline, painted: `dim`, `bold`, or a color like in `ColorChoice` (e.g. `green`).
+    Inline(Paint),
+    /// In the details below the record (`details`).
+    Details,
+    /// Not shown at all (`hidden`).
+    Hidden,
+}
+
+impl FromStr for FieldStyle {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let paint = match s.to_lowercase().as_str() {
+            "details" => return Ok(FieldStyle::Details),
+            "hidden" => return Ok(FieldStyle::Hidden),
+            "dim" => Paint {
+                dimmed: true,
+                ..Paint::default()
+            },
+            "bold" => Paint {
+                bold: true,
+                ..Paint::default()
+            },
+            _ => {
+                let invalid = |_| anyhow::anyhow!(format!("Invalid field style value: '{}'", s));
+                Paint {
+                    color: Some(parse_color(s).map_err(invalid)?),
+                    ..Paint::default()
+                }
+            }
+        };
+        Ok(FieldStyle::Inline(paint))
+    }
+}
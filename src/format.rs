@@ -0,0 +1,660 @@
+use crate::record::{paint_into, parse_error, LogRecord, ParsedLine};
+use crate::{
+    interrupted, Align, ColorDepth, ColorEscape, ColorTheme, Columns, Condition, FieldRenderers,
+    Format, InputFormat, LevelMap, LevelScheme, NamedLogLevel, QuoteStyle, Stats, TimeMode,
+    TimePrecision,
+};
+use chrono::{DateTime, TimeDelta, Utc};
+use std::borrow::Cow;
+
+/// All the knobs controlling how log lines are filtered and rendered.
+///
+/// ```rust
+/// use bunyan::{Format, FormatOptions, NamedLogLevel};
+///
+/// let options = FormatOptions {
+///     format: Format::Short,
+///     level: NamedLogLevel::Warn as u8,
+///     ..FormatOptions::default()
+/// };
+/// ```
+#[derive(Clone, Debug)]
+pub struct FormatOptions {
+    /// How records are read.
+    pub input_format: InputFormat,
+    /// The output format.
+    pub format: Format,
+    /// The columns of the table output format, see `Format::Table`.
+    pub columns: Columns,
+    /// How timestamps are displayed.
+    pub time: TimeMode,
+    /// The sub-second precision of displayed timestamps.
+    pub time_precision: TimePrecision,
+    /// If set, timestamps are displayed using this `strftime`-like format (see
+    /// `chrono::format::strftime`) in the long and short formats, regardless of
+    /// `time_precision`.
+    pub time_format: Option<String>,
+    /// Display timestamps as they were logged in the long and short formats, e.g. with their
+    /// original offset and precision, instead of reformatting them.
+    pub raw_time: bool,
+    /// The timezone of the timestamps recorded without one, e.g. `2012-02-08T22:56:52.856`.
+    pub assume_tz: TimeMode,
+    /// Records below this level are skipped.
+    pub level: u8,
+    /// Records above this level are skipped.
+    pub max_level: Option<u8>,
+    /// Custom names and colors for numerical levels.
+    pub levels: LevelMap,
+    /// The level of the records logged without one. In strict mode, such records are not bunyan
+    /// records.
+    pub default_level: u8,
+    /// The field holding the level of records, e.g. `severity`, when they have it: `level`
+    /// otherwise.
+    pub level_field: Option<String>,
+    /// How the levels read from records are interpreted, e.g. as syslog severities.
+    ///
+    /// Filters and `default_level` are always bunyan levels.
+    pub level_scheme: LevelScheme,
+    /// The field holding the message of records, e.g. `text`, when they have it: `msg`
+    /// otherwise, or `message` for records that do not have a `msg` either.
+    pub msg_field: Option<String>,
+    /// Level names are right-aligned in a column this wide, so that the following colons line
+    /// up. Longer names are not truncated.
+    pub level_width: usize,
+    /// Pad the prefix of records (time, level and origin) so that their messages line up,
+    /// escape sequences excluded. With `None`, messages follow their prefix.
+    pub align: Option<Align>,
+    /// Records for which the condition is false are skipped.
+    pub condition: Option<Condition>,
+    /// Records logged before this time are skipped.
+    pub since: Option<DateTime<Utc>>,
+    /// Records logged after this time are skipped.
+    pub until: Option<DateTime<Utc>>,
+    /// Assume records are sorted by time: stop reading an input as soon as a record logged after
+    /// `until` shows up, instead of going through it until its end.
+    ///
+    /// It has no effect when following files.
+    pub assume_sorted: bool,
+    /// Sort records by time, holding on to up to this many records to put them back in order.
+    ///
+    /// Records are written once they are out of the window, or at the end of the input.
+    pub sort_window: Option<usize>,
+    /// Stop reading the input once this many records have passed the filters.
+    pub max_count: Option<usize>,
+    /// Also show this many records before each record that passed the filters, like
+    /// `grep -B`.
+    pub before_context: usize,
+    /// Also show this many records after each record that passed the filters, like `grep -A`.
+    pub after_context: usize,
+    /// If not empty, only records coming from one of these processes are shown.
+    pub pids: Vec<u32>,
+    /// If not empty, only records coming from one of these loggers are shown.
+    pub names: Vec<String>,
+    /// Match `names` as substrings of the name of the logger, instead of requiring an exact match.
+    pub name_substring: bool,
+    /// If not empty, only records coming from one of these hosts are shown.
+    pub hostnames: Vec<String>,
+    /// If set, only records whose message matches (or, with `invert_grep`, does not match) this
+    /// regular expression are shown.
+    pub grep: Option<regex::Regex>,
+    /// Skip the records whose message matches `grep`, instead of keeping them.
+    pub invert_grep: bool,
+    /// Prefix records with the number of their line in the input (e.g. `3: `), counting all
+    /// lines. Inputs are numbered separately.
+    pub line_numbers: bool,
+    /// Do not print anything: only the exit status tells whether records passed the filters.
+    pub quiet: bool,
+    /// Do not print anything, but report each line that is not a bunyan record on stderr,
+    /// with its line number.
+    ///
+    /// Line numbers are counted in each input, as for `FormatOptions::line_numbers`.
+    pub validate: bool,
+    /// Skip lines that are not bunyan records, instead of passing them through.
+    ///
+    /// Records with duplicate keys or without a level are not bunyan records in strict mode.
+    pub strict: bool,
+    /// Do not format anything: lines are written as they are read, without even being parsed.
+    ///
+    /// Filters and the other formatting options have no effect.
+    pub raw: bool,
+    /// If set, lines that are not bunyan records are passed through prefixed with this marker,
+    /// so that they stand out from the records.
+    pub raw_marker: Option<String>,
+    /// Read records as whitespace-separated JSON values instead of one per line, so that
+    /// pretty-printed records spanning several lines are supported.
+    ///
+    /// Input that is not JSON cannot be passed through in this mode: it is an error.
+    pub multiline: bool,
+    /// Join lines that are not JSON because a string in them was cut by raw newlines (e.g.
+    /// `{"msg":"first` and `second"}`) with the following ones, up to a few lines, instead of
+    /// handling them as text.
+    ///
+    /// Only inputs that are read line by line, one after the other, are joined this way.
+    pub lenient: bool,
+    /// Report on stderr why lines that look like records, i.e. JSON objects, are not, see
+    /// `FormattedLine::ParseError`.
+    pub debug_parse: bool,
+    /// If set, records are not written as they are read but held, grouped by the value of this
+    /// field (e.g. `name`, or `req.id` for nested fields), for each group to be written under a
+    /// header once the inputs are read.
+    ///
+    /// It applies to the inputs processed by `process_stdin`, `process_files` and
+    /// `merge_files`, not to single lines formatted by `format_line`.
+    ///
+    /// All the records are held in memory in this mode: it is not suited to endless inputs.
+    pub group_by: Option<String>,
+    /// The colors used when the output is colorized.
+    pub theme: ColorTheme,
+    /// Paint the time and origin (logger name, pid, hostname) of the records like their level,
+    /// so that e.g. errors stand out.
+    pub highlight_level: bool,
+    /// If set, only the value at this JSON pointer (e.g. `/req/headers/host`) is shown for each
+    /// record, instead of the record itself: strings are shown without quotes, other values as
+    /// JSON.
+    ///
+    /// Records without such a value are skipped, as are the lines that are not records.
+    pub extract: Option<String>,
+    /// If set, only these extra fields are shown, in this order.
+    pub fields: Option<Vec<String>>,
+    /// These extra fields are not shown.
+    pub exclude_fields: Vec<String>,
+    /// Show the leaves of nested objects and arrays as inline extra fields, keyed by their
+    /// dotted path (e.g. `req.headers.host`, `tags.0`), instead of the nested values.
+    pub flatten: bool,
+    /// Show the logger name of the records in the long format.
+    pub show_name: bool,
+    /// Show the pid of the records in the long format.
+    pub show_pid: bool,
+    /// Show the hostname of the records in the long format.
+    pub show_hostname: bool,
+    /// Show the log format version (`v`) of the records for which it is not the expected one.
+    pub show_version: bool,
+    /// Color the values of inline extra fields according to their type. Booleans and nulls are
+    /// colored regardless.
+    pub color_values: bool,
+    /// Custom renderings of extra fields, by name.
+    pub field_renderers: FieldRenderers,
+    /// The separator between inline extra fields.
+    pub extras_separator: String,
+    /// Wrap inline extra fields in parentheses.
+    pub extras_wrap: bool,
+    /// When string values of extra fields are quoted, inline and in the logfmt output format.
+    pub quote_style: QuoteStyle,
+    /// Durations (`latency`, `responseTime` and `duration` fields, in milliseconds) above this
+    /// value are highlighted.
+    pub slow_threshold: Option<f64>,
+    /// Extra fields whose rendered value is longer than this many characters, or spans several
+    /// lines, are moved to the details block below the record instead of being shown inline.
+    ///
+    /// With `None`, everything is shown inline and each record fits on a single line.
+    pub details_threshold: Option<usize>,
+    /// Extra field values longer than this many bytes, once rendered, are truncated.
+    ///
+    /// Truncated values end with an ellipsis and their original length.
+    pub truncate_values: Option<usize>,
+    /// The indentation width of JSON values (e.g. nested objects) in the details.
+    ///
+    /// The details themselves are indented by twice this width.
+    pub json_indent: usize,
+    /// Pretty-print the bodies of HTTP requests and responses (`req`, `res`, etc.) that are
+    /// JSON, even when logged as strings, with `json_indent`. Other bodies are shown as they
+    /// are.
+    pub pretty_req_body: bool,
+    /// Inline extra fields that would make the line wider than this many characters are moved
+    /// to the details block below the record.
+    ///
+    /// It has no effect if `details_threshold` is `None`.
+    /// In the table output format, messages are cut to fit within this many characters instead.
+    pub max_width: Option<usize>,
+    /// Colorize the output with ANSI escape sequences, see `theme`.
+    pub color: bool,
+    /// How many colors the terminal supports.
+    pub color_depth: ColorDepth,
+    /// How colors are rendered.
+    pub color_escape: ColorEscape,
+}
+
+impl FormatOptions {
+    /// Whether a record passes all the active filters.
+    pub(crate) fn matches(&self, record: &LogRecord) -> bool {
+        record.level >= self.level
+            && self.max_level.is_none_or(|max| record.level <= max)
+            && self.since.is_none_or(|since| record.time >= since)
+            && self.until.is_none_or(|until| record.time <= until)
+            && (self.pids.is_empty() || record.pid.is_some_and(|pid| self.pids.contains(&pid)))
+            && (self.names.is_empty()
+                || record
+                    .name
+                    .as_deref()
+                    .is_some_and(|name| self.matches_name(name)))
+            && (self.hostnames.is_empty()
+                || record
+                    .hostname
+                    .as_deref()
+                    .is_some_and(|hostname| self.hostnames.iter().any(|h| h == hostname)))
+            && self
+                .grep
+                .as_ref()
+                .is_none_or(|grep| grep.is_match(&record.message) != self.invert_grep)
+            && self.condition.as_ref().is_none_or(|c| c.matches(record))
+    }
+
+    fn matches_name(&self, name: &str) -> bool {
+        self.names.iter().any(|pattern| {
+            if self.name_substring {
+                name.contains(pattern.as_str())
+            } else {
+                name == pattern
+            }
+        })
+    }
+
+    /// The extra fields of a record that should be shown, see `fields` and `exclude_fields`.
+    pub(crate) fn select_extras<'a>(
+        &self,
+        extras: &'a serde_json::Map<String, serde_json::Value>,
+    ) -> Cow<'a, serde_json::Map<String, serde_json::Value>> {
+        if self.fields.is_none() && self.exclude_fields.is_empty() {
+            return Cow::Borrowed(extras);
+        }
+        let selected = match &self.fields {
+            Some(fields) => fields
+                .iter()
+                .filter_map(|field| Some((field.clone(), extras.get(field)?.clone())))
+                .collect(),
+            None => extras.clone(),
+        };
+        Cow::Owned(
+            selected
+                .into_iter()
+                .filter(|(key, _)| !self.exclude_fields.contains(key))
+                .collect(),
+        )
+    }
+
+    /// Whether some records might not pass the filters.
+    pub fn has_filters(&self) -> bool {
+        self.level > NamedLogLevel::Trace as u8
+            || self.max_level.is_some()
+            || self.since.is_some()
+            || self.until.is_some()
+            || !self.pids.is_empty()
+            || !self.names.is_empty()
+            || !self.hostnames.is_empty()
+            || self.grep.is_some()
+            || self.condition.is_some()
+    }
+
+    /// Whether records that do not pass the filters might be shown, see `before_context` and
+    /// `after_context`.
+    pub(crate) fn has_context(&self) -> bool {
+        self.before_context > 0 || self.after_context > 0
+    }
+
+    /// Whether enough records passed the filters for nothing else to be read, see `max_count`,
+    /// or the user asked us to stop, see `catch_interrupts`.
+    pub(crate) fn is_done(&self, stats: &Stats) -> bool {
+        self.max_count.is_some_and(|count| stats.matched >= count) || interrupted()
+    }
+
+    /// Whether nothing past this line should be read, see `FormatOptions::assume_sorted`.
+    pub(crate) fn ends_input(&self, line: &ParsedLine) -> bool {
+        match (line, self.until) {
+            (ParsedLine::Record(record), Some(until)) => self.assume_sorted && record.time > until,
+            _ => false,
+        }
+    }
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            input_format: InputFormat::Auto,
+            format: Format::Long,
+            columns: Columns::default(),
+            time: TimeMode::Local,
+            time_precision: TimePrecision::Millis,
+            time_format: None,
+            raw_time: false,
+            assume_tz: TimeMode::Utc,
+            level: NamedLogLevel::Trace as u8,
+            max_level: None,
+            levels: LevelMap::default(),
+            default_level: NamedLogLevel::Info as u8,
+            level_field: None,
+            level_scheme: LevelScheme::Bunyan,
+            msg_field: None,
+            level_width: 5,
+            align: None,
+            condition: None,
+            since: None,
+            until: None,
+            assume_sorted: false,
+            sort_window: None,
+            max_count: None,
+            before_context: 0,
+            after_context: 0,
+            pids: Vec::new(),
+            names: Vec::new(),
+            name_substring: false,
+            hostnames: Vec::new(),
+            grep: None,
+            invert_grep: false,
+            line_numbers: false,
+            quiet: false,
+            validate: false,
+            strict: false,
+            raw: false,
+            raw_marker: None,
+            multiline: false,
+            lenient: false,
+            debug_parse: false,
+            group_by: None,
+            theme: ColorTheme::default(),
+            highlight_level: false,
+            extract: None,
+            fields: None,
+            exclude_fields: Vec::new(),
+            flatten: false,
+            color_values: false,
+            field_renderers: FieldRenderers::default(),
+            extras_separator: ",".into(),
+            extras_wrap: true,
+            quote_style: QuoteStyle::Minimal,
+            show_name: true,
+            show_pid: true,
+            show_hostname: true,
+            show_version: false,
+            slow_threshold: None,
+            details_threshold: Some(50),
+            truncate_values: None,
+            json_indent: 2,
+            pretty_req_body: false,
+            max_width: None,
+            color: false,
+            color_depth: ColorDepth::TrueColor,
+            color_escape: ColorEscape::Ansi,
+        }
+    }
+}
+
+/// What rendering a record depends on besides the record itself and the options: the records
+/// rendered before it, e.g. for relative times (see `TimeMode::Relative`), aligned messages
+/// (see `Align::Widest`) or table columns (see `Columns`).
+///
+/// `format_line` renders each line on its own: use `format_line_with_state`, with the same
+/// state for all the lines of an input, for them to be rendered relative to each other.
+#[derive(Clone, Debug, Default)]
+pub struct FormatState {
+    /// The time of the first record, that relative times are relative to.
+    time_baseline: Option<DateTime<Utc>>,
+    /// The widest prefix of a message so far, see `Align::Widest`.
+    widest_prefix: usize,
+    /// The width of the widest cell of each table column so far, see `Columns`.
+    column_widths: Vec<usize>,
+}
+
+impl FormatState {
+    /// The state of an input before any record.
+    pub fn new() -> Self {
+        FormatState::default()
+    }
+
+    /// Start relative times over, e.g. for another file: the next record becomes the first one.
+    pub fn reset_time_baseline(&mut self) {
+        self.time_baseline = None;
+    }
+
+    /// The time elapsed between the first record and `time`, the latter being the time of the
+    /// first record if there was none yet.
+    pub(crate) fn elapsed(&mut self, time: DateTime<Utc>) -> TimeDelta {
+        time - *self.time_baseline.get_or_insert(time)
+    }
+
+    /// The column messages start at, once widened to fit a prefix `width` characters wide.
+    pub(crate) fn widen_prefix(&mut self, width: usize) -> usize {
+        self.widest_prefix = self.widest_prefix.max(width);
+        self.widest_prefix
+    }
+
+    /// The width of the `index`-th table column, once widened to fit a cell `width` characters
+    /// wide.
+    pub(crate) fn widen_column(&mut self, index: usize, width: usize) -> usize {
+        if self.column_widths.len() <= index {
+            self.column_widths.resize(index + 1, 0);
+        }
+        self.column_widths[index] = self.column_widths[index].max(width);
+        self.column_widths[index]
+    }
+
+    /// The width of the `index`-th table column so far.
+    pub(crate) fn column_width(&self, index: usize) -> usize {
+        self.column_widths.get(index).copied().unwrap_or(0)
+    }
+}
+
+/// The error returned when a log line cannot be rendered.
+#[derive(Debug)]
+pub enum FormatError {
+    /// The record could not be serialized (e.g. with `Format::Json`).
+    Serialization(serde_json::Error),
+}
+
+impl std::fmt::Display for FormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FormatError::Serialization(e) => write!(f, "Failed to serialize the record: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FormatError::Serialization(e) => Some(e),
+        }
+    }
+}
+
+/// Filter and render a single line of input (without its trailing newline).
+///
+/// A trailing carriage return, as in lines ending with `"\r\n"`, is ignored.
+///
+/// It returns the text to be printed, trailing newline included, or `None` if the line
+/// should be skipped: records that do not pass the filters and, in strict mode, lines that are
+/// not bunyan records. Otherwise, lines that are not bunyan records are passed through as they
+/// are.
+///
+/// ```rust
+/// use bunyan::{format_line, FormatOptions, TimeMode};
+///
+/// let options = FormatOptions {
+///     time: TimeMode::Utc,
+///     ..FormatOptions::default()
+/// };
+/// let line = r#"{"name":"app","pid":1,"hostname":"box","level":30,"msg":"Hi","time":"2012-02-08T22:56:52.856Z","v":0}"#;
+/// assert_eq!(
+///     format_line(line, &options).unwrap().as_deref(),
+///     Some("[2012-02-08T22:56:52.856Z]  INFO: app/1 on box: Hi\n")
+/// );
+///
+/// let options = FormatOptions {
+///     level: 40,
+///     ..options
+/// };
+/// assert_eq!(format_line(line, &options).unwrap(), None);
+/// ```
+pub fn format_line(input: &str, options: &FormatOptions) -> Result<Option<String>, FormatError> {
+    format_line_with_state(input, options, &mut FormatState::new())
+}
+
+/// Like `format_line`, rendering the line after the ones rendered before with `state`.
+///
+/// ```rust
+/// use bunyan::{format_line_with_state, Format, FormatOptions, FormatState, TimeMode};
+///
+/// let options = FormatOptions {
+///     format: Format::Short,
+///     time: TimeMode::Relative,
+///     ..FormatOptions::default()
+/// };
+/// let mut state = FormatState::new();
+/// let first = r#"{"level":30,"msg":"Hi","time":"2012-02-08T22:56:52.856Z"}"#;
+/// let second = r#"{"level":30,"msg":"Bye","time":"2012-02-08T22:56:54.000Z"}"#;
+/// assert_eq!(
+///     format_line_with_state(first, &options, &mut state).unwrap().as_deref(),
+///     Some("+0.000  INFO: Hi\n")
+/// );
+/// assert_eq!(
+///     format_line_with_state(second, &options, &mut state).unwrap().as_deref(),
+///     Some("+1.144  INFO: Bye\n")
+/// );
+/// ```
+pub fn format_line_with_state(
+    input: &str,
+    options: &FormatOptions,
+    state: &mut FormatState,
+) -> Result<Option<String>, FormatError> {
+    let input = input.strip_suffix('\r').unwrap_or(input);
+    let mut formatted = String::new();
+    let written = format_parsed_line(
+        input,
+        &ParsedLine::parse(input, options),
+        options,
+        state,
+        &mut formatted,
+    )?;
+    Ok(written.then_some(formatted))
+}
+
+/// The outcome of filtering and rendering a single line of input, see `format_line_detailed`.
+#[derive(Debug)]
+pub enum FormattedLine {
+    /// A record that passed the filters, as rendered.
+    Record(String),
+    /// A line that is not a record, as rendered: it is passed through as it is.
+    Passthrough(String),
+    /// A line that looks like a record, i.e. a JSON object, but is not one: `source` tells
+    /// why, e.g. where the JSON is malformed or which field is missing.
+    ///
+    /// `line` is the line itself: `format_line` passes such lines through, unless in strict
+    /// mode.
+    ParseError {
+        line: String,
+        source: serde_json::Error,
+    },
+}
+
+/// Like `format_line`, telling records from the lines that are passed through, and the reason
+/// why lines that look like records are not.
+///
+/// ```rust
+/// use bunyan::{format_line_detailed, Format, FormatOptions, FormattedLine};
+///
+/// let options = FormatOptions {
+///     format: Format::Simple,
+///     ..FormatOptions::default()
+/// };
+/// let record = r#"{"level":30,"msg":"Hi","time":"2012-02-08T22:56:52.856Z"}"#;
+/// assert!(matches!(
+///     format_line_detailed(record, &options).unwrap(),
+///     Some(FormattedLine::Record(formatted)) if formatted == " INFO: Hi\n"
+/// ));
+/// assert!(matches!(
+///     format_line_detailed("Starting up", &options).unwrap(),
+///     Some(FormattedLine::Passthrough(formatted)) if formatted == "Starting up\n"
+/// ));
+/// match format_line_detailed(r#"{"level":30,"msg":"Hi"}"#, &options).unwrap() {
+///     Some(FormattedLine::ParseError { source, .. }) => {
+///         assert_eq!(source.to_string(), "missing field `time` at line 1 column 23")
+///     }
+///     other => panic!("unexpected outcome: {:?}", other),
+/// }
+/// ```
+pub fn format_line_detailed(
+    input: &str,
+    options: &FormatOptions,
+) -> Result<Option<FormattedLine>, FormatError> {
+    let input = input.strip_suffix('\r').unwrap_or(input);
+    let parsed = ParsedLine::parse(input, options);
+    if !matches!(parsed, ParsedLine::Record(_)) {
+        if let Some(source) = parse_error(input, options) {
+            return Ok(Some(FormattedLine::ParseError {
+                line: input.to_owned(),
+                source,
+            }));
+        }
+    }
+    let mut formatted = String::new();
+    if !format_parsed_line(
+        input,
+        &parsed,
+        options,
+        &mut FormatState::new(),
+        &mut formatted,
+    )? {
+        return Ok(None);
+    }
+    Ok(Some(match parsed {
+        ParsedLine::Record(_) => FormattedLine::Record(formatted),
+        ParsedLine::Json | ParsedLine::Text => FormattedLine::Passthrough(formatted),
+    }))
+}
+
+/// Render a record regardless of the filters, appending the text to be printed to `buffer`.
+///
+/// It returns whether anything was written: with `FormatOptions::extract`, records without
+/// the value are skipped.
+pub(crate) fn format_record(
+    record: &LogRecord,
+    options: &FormatOptions,
+    state: &mut FormatState,
+    buffer: &mut String,
+) -> Result<bool, FormatError> {
+    match &options.extract {
+        None => record.format_with_state(buffer, options, state)?,
+        Some(pointer) => {
+            match record.extract(pointer) {
+                Some(serde_json::Value::String(s)) => buffer.push_str(&s),
+                Some(value) => buffer.push_str(&value.to_string()),
+                None => return Ok(false),
+            }
+            buffer.push('\n');
+        }
+    }
+    Ok(true)
+}
+
+/// Like `format_line`, for a line that has already been parsed, appending the text to be
+/// printed to `buffer`.
+///
+/// It returns whether anything was written, i.e. `false` if the line should be skipped.
+pub(crate) fn format_parsed_line(
+    input: &str,
+    parsed: &ParsedLine,
+    options: &FormatOptions,
+    state: &mut FormatState,
+    buffer: &mut String,
+) -> Result<bool, FormatError> {
+    match parsed {
+        ParsedLine::Record(r) => {
+            if !options.matches(r) {
+                return Ok(false);
+            }
+            format_record(r, options, state, buffer)
+        }
+        ParsedLine::Json | ParsedLine::Text => {
+            // The bunyan output format is meant to produce valid bunyan logs.
+            if options.strict || options.format == Format::Bunyan || options.extract.is_some() {
+                Ok(false)
+            } else {
+                if let Some(marker) = &options.raw_marker {
+                    paint_into(buffer, marker, options.theme.raw_marker, options);
+                }
+                buffer.push_str(input);
+                buffer.push('\n');
+                Ok(true)
+            }
+        }
+    }
+}
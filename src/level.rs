@@ -1,3 +1,5 @@
+use crate::style::parse_color;
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
 use std::str::FromStr;
 
@@ -44,19 +46,60 @@ pub enum NamedLogLevel {
     Trace = 10,
 }
 
+/// Lookup table between the names of the canonical log levels and their numerical values.
+pub const NAMED_LOG_LEVELS: [(&str, NamedLogLevel); 6] = [
+    ("trace", NamedLogLevel::Trace),
+    ("debug", NamedLogLevel::Debug),
+    ("info", NamedLogLevel::Info),
+    ("warn", NamedLogLevel::Warn),
+    ("error", NamedLogLevel::Error),
+    ("fatal", NamedLogLevel::Fatal),
+];
+
+/// The canonical log level matching each syslog severity (RFC 5424), from 0 (emergency) to
+/// 7 (debug), as used by e.g. GELF.
+pub const SYSLOG_LEVELS: [NamedLogLevel; 8] = [
+    NamedLogLevel::Fatal,
+    NamedLogLevel::Fatal,
+    NamedLogLevel::Fatal,
+    NamedLogLevel::Error,
+    NamedLogLevel::Warn,
+    NamedLogLevel::Info,
+    NamedLogLevel::Info,
+    NamedLogLevel::Debug,
+];
+
+impl NamedLogLevel {
+    /// The canonical log level of a syslog severity, see `SYSLOG_LEVELS`.
+    ///
+    /// Severities above 7 are not specified: they are as verbose as it gets.
+    pub fn from_syslog(severity: u8) -> Self {
+        SYSLOG_LEVELS
+            .get(severity as usize)
+            .copied()
+            .unwrap_or(NamedLogLevel::Trace)
+    }
+
+    /// The lowercase name of the level, e.g. "info".
+    pub fn name(self) -> &'static str {
+        NAMED_LOG_LEVELS
+            .iter()
+            .find(|(_, level)| *level == self)
+            .map(|(name, _)| *name)
+            .unwrap_or_default()
+    }
+}
+
 impl FromStr for NamedLogLevel {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_lowercase().as_str() {
-            "trace" => Ok(NamedLogLevel::Trace),
-            "debug" => Ok(NamedLogLevel::Debug),
-            "info" => Ok(NamedLogLevel::Info),
-            "warn" => Ok(NamedLogLevel::Warn),
-            "error" => Ok(NamedLogLevel::Error),
-            "fatal" => Ok(NamedLogLevel::Fatal),
-            _ => Err(anyhow::anyhow!(format!("Invalid level value: '{}'", s))),
-        }
+        let lowercase = s.to_lowercase();
+        NAMED_LOG_LEVELS
+            .iter()
+            .find(|(name, _)| *name == lowercase)
+            .map(|(_, level)| *level)
+            .ok_or_else(|| anyhow::anyhow!(format!("Invalid level value: '{}'", s)))
     }
 }
 
@@ -64,17 +107,107 @@ impl TryFrom<u8> for NamedLogLevel {
     type Error = anyhow::Error;
 
     fn try_from(value: u8) -> Result<Self, anyhow::Error> {
-        match value {
-            10 => Ok(NamedLogLevel::Trace),
-            20 => Ok(NamedLogLevel::Debug),
-            30 => Ok(NamedLogLevel::Info),
-            40 => Ok(NamedLogLevel::Warn),
-            50 => Ok(NamedLogLevel::Error),
-            60 => Ok(NamedLogLevel::Fatal),
-            v => Err(anyhow::anyhow!(format!(
-                "{} does not correspond to a valid named log level.",
-                v
+        NAMED_LOG_LEVELS
+            .iter()
+            .find(|(_, level)| *level as u8 == value)
+            .map(|(_, level)| *level)
+            .ok_or_else(|| {
+                anyhow::anyhow!(format!(
+                    "{} does not correspond to a valid named log level.",
+                    value
+                ))
+            })
+    }
+}
+
+/// How the numerical levels of records are interpreted, see `FormatOptions::level_scheme`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum LevelScheme {
+    /// Bunyan levels, e.g. 30 for info, see `NamedLogLevel`.
+    #[default]
+    Bunyan,
+    /// Syslog severities, as exported by e.g. journald: from 0 (emergency) to 7 (debug), they
+    /// are mapped to the canonical levels, see `SYSLOG_LEVELS`.
+    Syslog,
+}
+
+impl LevelScheme {
+    /// The canonical interpretation of a numerical `level` read from a record.
+    ///
+    /// Levels above 7 are not syslog severities: they are left as they are, so that records
+    /// with named levels (e.g. `"warn"`) are not affected by the syslog scheme.
+    pub fn interpret(self, level: u8) -> u8 {
+        match self {
+            LevelScheme::Syslog if level <= 7 => NamedLogLevel::from_syslog(level) as u8,
+            _ => level,
+        }
+    }
+}
+
+impl FromStr for LevelScheme {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "bunyan" => Ok(LevelScheme::Bunyan),
+            "syslog" => Ok(LevelScheme::Syslog),
+            _ => Err(anyhow::anyhow!(format!(
+                "Invalid level scheme value: '{}'",
+                s
             ))),
         }
     }
 }
+
+/// Custom names and colors for numerical log levels (e.g. 25 for "notice"), extending or
+/// overriding the canonical ones (see `NamedLogLevel`).
+///
+/// It is parsed from a JSON object mapping levels to a name and an optional color, either a
+/// color name (e.g. `green`, `bright blue`) or an `r,g,b` triple:
+///
+/// ```json
+/// {"25": {"name": "notice", "color": "green"}, "35": {"name": "audit", "color": "255,128,0"}}
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LevelMap(BTreeMap<u8, CustomLevel>);
+
+/// The rendering of a custom log level.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CustomLevel {
+    pub name: String,
+    pub color: Option<colored::Color>,
+}
+
+impl LevelMap {
+    pub fn get(&self, level: u8) -> Option<&CustomLevel> {
+        self.0.get(&level)
+    }
+}
+
+impl FromStr for LevelMap {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        #[derive(serde::Deserialize)]
+        struct RawLevel {
+            name: String,
+            color: Option<String>,
+        }
+
+        let raw: BTreeMap<u8, RawLevel> = serde_json::from_str(s)
+            .map_err(|e| anyhow::anyhow!(format!("Invalid level map: {}", e)))?;
+        raw.into_iter()
+            .map(|(level, raw)| {
+                let color = raw.color.as_deref().map(parse_color).transpose()?;
+                Ok((
+                    level,
+                    CustomLevel {
+                        name: raw.name,
+                        color,
+                    },
+                ))
+            })
+            .collect::<Result<_, Self::Err>>()
+            .map(LevelMap)
+    }
+}
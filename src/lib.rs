@@ -1,8 +1,38 @@
+//! A Rust port of [node-bunyan](https://github.com/trentm/node-bunyan), to pretty-print
+//! structured logs.
+//!
+//! Besides powering the `bunyan` CLI, the crate can be used to embed bunyan formatting in your
+//! own log viewers, using [`format_line`], or [`format_line_detailed`] to also tell why lines
+//! are not records:
+//!
+//! ```rust
+//! use bunyan::{format_line, Format, FormatOptions, TimeMode};
+//!
+//! let options = FormatOptions {
+//!     format: Format::Short,
+//!     time: TimeMode::Utc,
+//!     ..FormatOptions::default()
+//! };
+//! let line = r#"{"name":"app","pid":1,"hostname":"box","level":30,"msg":"Hi","time":"2012-02-08T22:56:52.856Z","v":0}"#;
+//! assert_eq!(
+//!     format_line(line, &options).unwrap().as_deref(),
+//!     Some("22:56:52.856  INFO: Hi\n")
+//! );
+//! ```
+mod condition;
+mod field_renderers;
+mod format;
 mod level;
 mod record;
 mod sources;
 mod style;
+mod time_bound;
 
+pub use condition::*;
+pub use field_renderers::*;
+pub use format::*;
 pub use level::*;
+pub use record::{LogRecord, LOG_FORMAT_VERSION};
 pub use sources::*;
 pub use style::*;
+pub use time_bound::*;
@@ -1,50 +1,701 @@
-use bunyan::{process_stdin, Format, NumericalLogLevel};
+use anyhow::Context;
+use bunyan::{
+    catch_interrupts, follow_files, interrupted, listen, merge_files, process_files, process_stdin,
+    Align, ColorChoice, ColorDepth, ColorEscape, ColorTheme, Columns, Condition, FatalStyle,
+    FieldRenderers, FieldStyle, Format, FormatOptions, InputFormat, LevelMap, LevelScheme,
+    NumericalLogLevel, QuoteStyle, Stats, TimeBound, TimeMode, TimePrecision,
+};
+use chrono::format::{Item, StrftimeItems};
 use clap::Parser;
+use std::ffi::OsString;
+use std::fs::OpenOptions;
+use std::io::{BufWriter, IsTerminal, Write};
+use std::path::PathBuf;
 
 /// Bunyan is a simple and fast JSON logging library for node.js services,
 /// a one-JSON-object-per-line log format, and a bunyan CLI tool for nicely viewing those logs.
 /// this is a Rust implementation of bunyan cli used to filter and pretty-print Bunyan log file content.
+///
+/// Default options can be set in the BUNYAN_OPTS environment variable, e.g.
+/// `BUNYAN_OPTS='--time utc -l info'`: options on the command line take precedence, including
+/// over the opposite options (e.g. `--no-color` over `--color`).
 #[derive(Parser)]
-#[command(version = "0.1", author = "Luca Palmieri <rust@lpalmieri.com>")]
+#[command(
+    version = "0.1",
+    author = "Luca Palmieri <rust@lpalmieri.com>",
+    args_override_self = true
+)]
 struct Cli {
+    /// Log files to process, in order. `-` stands for stdin.
+    ///
+    /// Compressed files (gzip, zstd, bzip2) are decompressed.
+    ///
+    /// If none is specified, logs are read from stdin.
+    files: Vec<PathBuf>,
+    /// Keep the files open once their end is reached and format new lines as they are
+    /// appended, like `tail -f`.
+    ///
+    /// Truncated or rotated files are read again from the start. Hit Ctrl-C to stop: what has
+    /// been read so far is still written out, along with the `--stats` summary.
+    #[arg(short, long)]
+    follow: bool,
+    /// Listen for TCP connections on the specified address (e.g. "127.0.0.1:9000") and format
+    /// the logs sent by clients, one connection at a time.
+    #[arg(long, value_name = "ADDRESS", conflicts_with_all = ["files", "follow"])]
+    listen: Option<String>,
     /// Only show messages at or above the specified level.
     ///
     /// You can specify level names (trace, debug, info, warn, error, fatal) or a positive
     /// numeric value.
     #[arg(short, long, default_value = "trace")]
     level: NumericalLogLevel,
+    /// Only show messages at or below the specified level, e.g. `-l warn --level-max warn` for
+    /// warnings only.
+    ///
+    /// Levels are specified like for `--level`.
+    #[arg(long, value_name = "LEVEL")]
+    level_max: Option<NumericalLogLevel>,
+    /// A JSON file giving names and colors to custom numeric levels, e.g.
+    /// `{"25": {"name": "notice", "color": "green"}}`.
+    ///
+    /// Colors are either names (e.g. "bright blue") or `r,g,b` triples (e.g. "255,128,0").
+    /// Canonical levels can be overridden as well.
+    #[arg(long, value_name = "FILE")]
+    level_map: Option<PathBuf>,
+    /// The level of messages logged without one, specified like for `--level`.
+    ///
+    /// With `--strict`, such messages are not legal Bunyan log lines.
+    #[arg(long, default_value = "info", value_name = "LEVEL")]
+    default_level: NumericalLogLevel,
+    /// Read the level of messages from this field (e.g. `severity` or `lvl`) when they have it,
+    /// rather than from `level`.
+    #[arg(long, value_name = "NAME")]
+    level_field: Option<String>,
+    /// How the numerical levels of messages are interpreted:
+    ///
+    /// - bunyan: bunyan levels, e.g. 30 for info;
+    /// - syslog: syslog severities (e.g. journald exports), from 0 (emergency, shown as fatal)
+    ///   to 7 (debug).
+    ///
+    /// `--level` and `--default-level` are bunyan levels either way.
+    #[arg(long, default_value = "bunyan", value_name = "SCHEME")]
+    level_scheme: LevelScheme,
+    /// Read the text of messages from this field (e.g. `text`) when they have it, rather
+    /// than from `msg`.
+    ///
+    /// Messages without a `msg` field have theirs read from `message`, if any.
+    #[arg(long, value_name = "NAME")]
+    msg_field: Option<String>,
+    /// The width of the column of level names, e.g. to line up longer custom level names.
+    ///
+    /// Names are right-aligned: longer ones are not truncated.
+    #[arg(long, default_value_t = 5, value_name = "N")]
+    level_width: usize,
+    /// Line up messages: pad what comes before them (time, level, name/pid on host) so that
+    /// they start at the specified column.
+    ///
+    /// Without a column (or with `auto`), messages start after the widest prefix seen so far.
+    #[arg(
+        long,
+        value_name = "COLUMN",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "auto"
+    )]
+    align: Option<Align>,
+    /// Only show messages logged at or after the specified time.
+    ///
+    /// Either an RFC 3339 timestamp (e.g. "2012-02-08T22:56:52Z") or a time relative to now, in
+    /// seconds, minutes, hours or days (e.g. "-30s", "-10m", "-2h", "-1d").
+    #[arg(long, allow_hyphen_values = true)]
+    since: Option<TimeBound>,
+    /// Only show messages logged at or before the specified time.
+    ///
+    /// It accepts the same values as `--since`.
+    #[arg(long, allow_hyphen_values = true)]
+    until: Option<TimeBound>,
+    /// Assume messages are sorted by time: stop reading each input at the first message logged
+    /// after `--until`, instead of scanning it until its end.
+    #[arg(long, requires = "until", conflicts_with = "follow")]
+    assume_sorted: bool,
+    /// Sort messages by time, for logs that are slightly out of order (e.g. merged from several
+    /// sources).
+    ///
+    /// Messages are held back until enough of them have been read (see `--sort-window`):
+    /// they are not shown as soon as they are logged.
+    #[arg(long, conflicts_with = "follow")]
+    sort: bool,
+    /// How many messages `--sort` holds on to, i.e. how far out of order messages can be.
+    #[arg(long, default_value_t = 100, requires = "sort", value_name = "N")]
+    sort_window: usize,
+    /// Interleave the messages of all the files by time, for files that are each sorted by time
+    /// (e.g. the logs of several instances of a service).
+    ///
+    /// Lines that are not messages stick to the message they follow in their file.
+    #[arg(long, conflicts_with_all = ["follow", "sort", "multiline", "raw"])]
+    merge: bool,
+    /// Group messages by the value of the specified field (e.g. `name`, or `req.id` for nested
+    /// fields), each group under a header, in the order its first message was read. Messages
+    /// without the field come last, under `(ungrouped)`.
+    ///
+    /// All the messages are held until the input is read: they are not shown as they are
+    /// logged.
+    #[arg(long, value_name = "FIELD", value_parser = parse_group_by, conflicts_with_all = [
+        "follow", "listen", "sort", "raw", "validate", "after_context", "before_context",
+    ])]
+    group_by: Option<String>,
+    /// Stop once N messages have been shown, i.e. passed all the filters, like `head -n N`.
+    ///
+    /// When following files, wait for N messages and then exit.
+    #[arg(short = 'n', long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    count: Option<u64>,
+    /// Also show N messages after each message that passed the filters, like `grep -A`.
+    #[arg(short = 'A', long, default_value_t = 0, value_name = "N")]
+    after_context: usize,
+    /// Also show N messages before each message that passed the filters, like `grep -B`.
+    ///
+    /// Groups of messages that are not contiguous are separated by `--`.
+    #[arg(short = 'B', long, default_value_t = 0, value_name = "N")]
+    before_context: usize,
+    /// Only show messages from the process with the specified pid.
+    ///
+    /// It can be repeated to show messages from several processes.
+    #[arg(short, long = "pid")]
+    pids: Vec<u32>,
+    /// Only show messages from the logger with the specified name.
+    ///
+    /// It can be repeated to show messages from several loggers.
+    #[arg(long = "name")]
+    names: Vec<String>,
+    /// Show messages from the loggers whose name contains one of the `--name` values, instead of
+    /// requiring an exact match.
+    #[arg(long, requires = "names")]
+    name_substring: bool,
+    /// Only show messages from the host with the specified name.
+    ///
+    /// It can be repeated to show messages from several hosts.
+    #[arg(long = "hostname")]
+    hostnames: Vec<String>,
+    /// Only show messages whose `msg` matches the specified regular expression.
+    ///
+    /// Matches are highlighted when the output is colorized.
+    #[arg(long)]
+    grep: Option<regex::Regex>,
+    /// Only show messages whose `msg` does not match the `--grep` regular expression.
+    #[arg(short = 'v', long, requires = "grep")]
+    invert_grep: bool,
+    /// Run each log message through the condition and only show those that return true.
+    ///
+    /// E.g.: `-c 'this.pid == 123'`, `-c 'this.level == DEBUG'`,
+    /// `-c 'this.msg == "boom" || this.level >= 50'`.
+    /// Supported operators are `==`, `!=`, `<`, `<=`, `>`, `>=`, `&&` and `||`.
+    #[arg(short, long)]
+    condition: Option<Condition>,
+    /// Specify the input format.
+    ///
+    /// - auto: bunyan records, or similar ones (e.g. pino's);
+    /// - bunyan: bunyan records only, with ISO 8601 times;
+    /// - pino: pino records only, with times in milliseconds since the epoch;
+    /// - gelf: GELF messages (e.g. Graylog exports), `short_message` being the message and
+    ///   `_`-prefixed fields the extra fields.
+    ///
+    /// Lines that are not records of the input format are shown as they are.
+    #[arg(long, alias = "format", default_value = "auto")]
+    input_format: InputFormat,
     /// Specify an output format.
     ///
     /// - long: prettified JSON;
+    /// - short: like long, but without hostname, logger name and pid, and with time-of-day only;
+    /// - json: JSON output, 2-space indent;
+    /// - json-N: JSON output, N-space indent, e.g. "json-4" (N=0 is single-line);
+    /// - bunyan: single-line JSON output, dropping lines that are not bunyan records;
+    /// - inspect: the parsed record, as Rust debug output;
+    /// - logfmt: `key=value` pairs, e.g. `time=... level=info pid=123 msg="Hello world"`;
+    /// - simple: the level and message only, e.g. ` INFO: Hello world`;
+    /// - table: aligned columns, see `--columns`.
     #[arg(short, long, default_value = "long")]
     output: Format,
+    /// The columns of the table output format, among time, level, name, hostname, pid, msg and
+    /// `extras.` followed by the name of an extra field, e.g. `time,level,extras.req_id,msg`.
+    ///
+    /// Messages are cut to fit within `--max-width`. Extra fields are only shown as columns.
+    #[arg(long, default_value = "time,level,pid,msg", value_name = "COLUMNS")]
+    columns: Columns,
+    /// Write the formatted output to the specified file instead of stdout, truncating it first
+    /// unless `--append` is specified.
+    ///
+    /// Output written to a file is not colorized, unless `--color` is specified.
+    #[arg(short = 'w', long, value_name = "PATH")]
+    output_file: Option<PathBuf>,
+    /// Append to the `--output-file` instead of truncating it.
+    #[arg(long, requires = "output_file")]
+    append: bool,
     /// Colorize output.
     ///
-    /// Defaults to try if output stream is a TTY.
-    #[arg(long = "color", conflicts_with = "no-color")]
+    /// Defaults to try if output stream is a TTY and the `NO_COLOR` environment variable is
+    /// not set.
+    #[arg(long = "color", overrides_with = "no-color")]
     color: bool,
+    /// The number of colors supported by the terminal: 16, 256 or truecolor.
+    ///
+    /// Defaults to a guess based on the `COLORTERM` and `TERM` environment variables.
+    #[arg(long)]
+    color_depth: Option<ColorDepth>,
+    /// How colors are rendered: ansi (escape sequences) or visible (readable tokens, e.g.
+    /// `<bold+red>boom</>`, to debug colors).
+    ///
+    /// Visible colors are always shown, unless `--no-color` is specified.
+    #[arg(long, default_value = "ansi", value_name = "ESCAPE")]
+    color_escape: ColorEscape,
     /// Force no coloring (e.g. terminal doesn't support it).
-    #[arg(name = "no-color", long = "no-color", conflicts_with = "color")]
+    #[arg(name = "no-color", long = "no-color", overrides_with = "color")]
     no_color: bool,
+    /// The colors of the output: default, solarized or mono (bold only).
+    ///
+    /// The colors specified by `--msg-color` and `--level-map` take precedence.
+    #[arg(long, default_value = "default", value_name = "THEME")]
+    color_theme: ColorTheme,
+    /// The color of log messages: a color name (e.g. "green", "bright blue"), an `r,g,b` triple
+    /// (e.g. "255,128,0") or "none" to leave them uncolored.
+    ///
+    /// Defaults to the color of the theme, cyan for the default one.
+    #[arg(long)]
+    msg_color: Option<ColorChoice>,
+    /// The style of the FATAL level: reversed, bold-red or white-on-red.
+    ///
+    /// Defaults to the style of the theme, reversed for the default one.
+    #[arg(long, value_name = "STYLE")]
+    fatal_style: Option<FatalStyle>,
+    /// Prefix the lines that are not bunyan messages with this marker, e.g. `--mark-raw '| '`,
+    /// to tell them apart from messages.
+    #[arg(long, value_name = "MARKER")]
+    mark_raw: Option<String>,
+    /// The color of the marker of `--mark-raw`, like `--msg-color`.
+    ///
+    /// Defaults to the color of the theme, dimmed for the default one.
+    #[arg(long, value_name = "COLOR")]
+    mark_raw_color: Option<ColorChoice>,
+    /// Color the time, logger name, pid and hostname of messages like their level, so that
+    /// e.g. errors stand out. Messages keep their own color.
+    #[arg(long)]
+    highlight_level: bool,
+    /// Prefix messages with the number of their line in the file, e.g. `3: `, to find them in
+    /// the raw logs.
+    #[arg(short = 'N', long)]
+    line_numbers: bool,
+    /// Do not print anything: exit with status 1 if filters are specified (e.g. `-l`, `-c`,
+    /// `--grep`) and no message passed them, like `grep -q`.
+    ///
+    /// Invalid arguments and files that cannot be opened take precedence, with exit status 2.
+    #[arg(short, long)]
+    quiet: bool,
+    /// Once all the input has been processed (or on Ctrl-C), print on stderr how many messages
+    /// were read, by level, and how many lines were not bunyan messages.
+    #[arg(long)]
+    stats: bool,
     /// Suppress all but legal Bunyan JSON log lines. By default non-JSON and non-Bunyan lines
     /// are passed through.
+    ///
+    /// Records with duplicate keys (e.g. two `msg` fields) or without a level are not legal
+    /// either.
     #[arg(long)]
     strict: bool,
-    /// Show timestamps in UTC.
-    #[arg(name = "utc", long = "show-utc")]
+    /// Only check that the input is made of legal Bunyan JSON log lines (see `--strict`): print
+    /// nothing but the line numbers of the other lines, on stderr, and exit with status 1 if
+    /// there are any.
+    #[arg(long, conflicts_with_all = ["quiet", "raw", "follow"])]
+    validate: bool,
+    /// Do not format anything: echo the input as it is, e.g. to temporarily make `bunyan`
+    /// transparent in a pipeline.
+    ///
+    /// Filters and formatting options have no effect.
+    #[arg(long, conflicts_with_all = ["quiet", "sort", "multiline"])]
+    raw: bool,
+    /// Read records spanning several lines, e.g. pretty-printed JSON objects, instead of one
+    /// record per line.
+    ///
+    /// All the input must be JSON: lines that are not cannot be passed through and make
+    /// bunyan stop with an error.
+    #[arg(long, conflicts_with = "follow")]
+    multiline: bool,
+    /// Join records cut by raw newlines in their strings (invalid JSON written by some
+    /// loggers), e.g. `{"msg":"first` and `second"}`, instead of passing their lines through.
+    ///
+    /// Up to 10 lines are joined to the first line of a record.
+    #[arg(long, conflicts_with_all = ["multiline", "follow", "merge"])]
+    lenient: bool,
+    /// Print why lines that look like messages (JSON objects) are not legal Bunyan log lines on
+    /// stderr, e.g. where the JSON is malformed or which field is missing, along with their
+    /// line numbers.
+    #[arg(long)]
+    debug_parse: bool,
+    /// Only show the value at the specified JSON pointer for each message, e.g.
+    /// `--extract /req/headers/x-request-id`, one per line. Strings are shown without quotes.
+    ///
+    /// Messages without such a value and lines that are not bunyan messages are skipped.
+    #[arg(long, value_name = "POINTER", value_parser = parse_json_pointer)]
+    extract: Option<String>,
+    /// Only show the specified extra fields, in the specified order, e.g. `--fields req_id,user`.
+    ///
+    /// The time, level, logger name, pid, hostname and message are always shown.
+    #[arg(long, value_delimiter = ',', value_name = "FIELDS")]
+    fields: Option<Vec<String>>,
+    /// Do not show the specified extra fields, e.g. `--exclude-fields req,res`.
+    #[arg(long, value_delimiter = ',', value_name = "FIELDS")]
+    exclude_fields: Vec<String>,
+    /// Show nested extra fields as dotted keys, e.g. `req.headers.host=example.com` or
+    /// `tags.0=a`, instead of JSON values.
+    #[arg(long)]
+    flatten: bool,
+    /// Color the values of the extra fields shown after the message according to their type:
+    /// strings in green and numbers in yellow.
+    ///
+    /// Booleans (true in green, false in red) and null (dimmed) are colored regardless.
+    #[arg(long)]
+    color_values: bool,
+    /// Render an extra field in a specific way, e.g. `--render trace_id=hidden`: `hidden`,
+    /// `details` (below the record), `dim`, `bold` or a color like `--msg-color` (after the
+    /// message). Can be repeated.
+    ///
+    /// `trace_id` and `span_id` are dimmed by default.
+    #[arg(long, value_name = "KEY=STYLE", value_parser = parse_field_style)]
+    render: Vec<(String, FieldStyle)>,
+    /// The separator between extra fields shown after the message, e.g. `--extras-sep ' '`.
+    #[arg(long = "extras-sep", default_value = ",", value_name = "SEPARATOR")]
+    extras_separator: String,
+    /// Whether to wrap the extra fields shown after the message in parentheses.
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set, value_name = "BOOL")]
+    extras_wrap: bool,
+    /// When string values of extra fields are quoted, e.g. for logfmt parsers: minimal (when
+    /// they are empty or have spaces), always or never.
+    ///
+    /// Quoted values are escaped like JSON strings. Values with newlines are quoted regardless
+    /// in the outputs keeping each message on a single line (`-o logfmt`, `--no-details`).
+    #[arg(long, default_value = "minimal", value_name = "STYLE")]
+    quote_style: QuoteStyle,
+    /// Do not show the logger name of messages in the long format.
+    #[arg(long)]
+    no_name: bool,
+    /// Do not show the pid of messages in the long format.
+    #[arg(long)]
+    no_pid: bool,
+    /// Do not show the hostname of messages in the long format, e.g. when all logs come from
+    /// the same host.
+    #[arg(long)]
+    no_host: bool,
+    /// Show the log format version (`v`) of messages that were not logged using the current
+    /// version of the bunyan format (0), i.e. that might not be formatted correctly.
+    #[arg(long)]
+    show_version: bool,
+    /// Highlight durations (`latency`, `responseTime` and `duration` fields, in milliseconds)
+    /// above this value.
+    #[arg(long, value_name = "MS")]
+    slow_threshold: Option<f64>,
+    /// Extra fields longer than this many characters are moved below the record, instead of
+    /// being shown inline after the message. Multi-line values are always moved.
+    #[arg(long, default_value_t = 50, overrides_with = "no_details")]
+    details_threshold: usize,
+    /// Show all extra fields inline, so that each record fits on a single line (e.g. for
+    /// grepping).
+    #[arg(long, overrides_with = "details_threshold")]
+    no_details: bool,
+    /// Extra fields that would make the line of the record wider than this many characters are
+    /// moved below the record, instead of being shown inline after the message.
+    ///
+    /// In the table output format, messages are cut to fit instead.
+    ///
+    /// Defaults to the width of the terminal, if the output is a terminal (not an
+    /// `--output-file`). 0 means no limit.
+    #[arg(long, value_name = "N")]
+    max_width: Option<usize>,
+    /// Truncate the values of extra fields longer than N bytes, e.g. base64-encoded blobs.
+    #[arg(long, value_name = "N")]
+    truncate_values: Option<usize>,
+    /// The indentation width of JSON values (e.g. nested objects) shown below records.
+    ///
+    /// These values are themselves indented by twice this width.
+    #[arg(long, default_value_t = 2, value_name = "N")]
+    json_indent: usize,
+    /// Pretty-print the bodies of HTTP requests and responses (`req`, `res`, etc.) that are
+    /// JSON, even when they are logged as strings. Other bodies are shown as they are.
+    #[arg(long)]
+    pretty_req_body: bool,
+    /// Display timestamps in the specified timezone.
+    ///
+    /// - local: the local timezone of this machine;
+    /// - utc: UTC, as recorded in the logs;
+    /// - relative: the seconds elapsed since the first record shown, e.g. "+1.234". It starts
+    ///   over for each file, unless they are merged (see `--merge`) or followed.
+    #[arg(long, default_value = "local")]
+    time: TimeMode,
+    /// The sub-second precision of timestamps: s, ms, us or ns.
+    #[arg(long, default_value = "ms", overrides_with = "raw_time")]
+    time_precision: TimePrecision,
+    /// A custom layout of timestamps, e.g. "%Y-%m-%d %H:%M:%S": see
+    /// https://docs.rs/chrono/latest/chrono/format/strftime/ for the supported specifiers.
+    ///
+    /// It takes precedence over `--time-precision`, but not over `--time`.
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        value_parser = parse_time_format,
+        overrides_with = "raw_time"
+    )]
+    time_format: Option<String>,
+    /// Show timestamps exactly as they were logged (e.g. "2012-02-08T23:56:52.856123+01:00"),
+    /// instead of in the timezone and precision picked by the options above.
+    #[arg(long, overrides_with_all = ["time_format", "time_precision"])]
+    raw_time: bool,
+    /// The timezone of timestamps recorded without one (e.g. "2012-02-08T22:56:52.856"): utc or
+    /// local.
+    #[arg(
+        long,
+        default_value = "utc",
+        value_name = "TIMEZONE",
+        value_parser = parse_timezone
+    )]
+    assume_tz: TimeMode,
+    /// Show timestamps in UTC. Same as `--time utc`, as in node-bunyan.
+    #[arg(short, long, alias = "show-utc")]
     utc: bool,
 }
 
-fn main() {
-    let cli = Cli::parse();
+/// Nested fields are separated by dots, e.g. `req.id`: no part of the path can be empty.
+fn parse_group_by(s: &str) -> anyhow::Result<String> {
+    if s.is_empty() || s.split('.').any(str::is_empty) {
+        return Err(anyhow::anyhow!(format!("Invalid group by value: '{}'", s)));
+    }
+    Ok(s.to_string())
+}
 
-    // Color output if explicitly requested or if the terminal supports it, unless the user
-    // explicitly opted out.
-    if cli.no_color {
-        colored::control::set_override(false);
-    } else if cli.color || atty::is(atty::Stream::Stdout) {
-        colored::control::set_override(true);
+/// A JSON pointer is either empty (the whole value) or made of `/`-prefixed keys.
+fn parse_json_pointer(s: &str) -> anyhow::Result<String> {
+    if s.is_empty() || s.starts_with('/') {
+        Ok(s.to_string())
+    } else {
+        Err(anyhow::anyhow!(format!(
+            "Invalid JSON pointer value: '{}'",
+            s
+        )))
     }
+}
 
-    process_stdin(cli.output, cli.level.0, cli.strict, cli.utc);
+/// A field and how to render it, as `key=style`.
+fn parse_field_style(s: &str) -> anyhow::Result<(String, FieldStyle)> {
+    match s.split_once('=') {
+        Some((key, style)) if !key.is_empty() => Ok((key.to_string(), style.parse()?)),
+        _ => Err(anyhow::anyhow!(format!("Invalid render value: '{}'", s))),
+    }
+}
+
+/// A timezone, as opposed to relative times.
+fn parse_timezone(s: &str) -> anyhow::Result<TimeMode> {
+    match s.parse()? {
+        TimeMode::Relative => Err(anyhow::anyhow!(format!("Invalid timezone value: '{}'", s))),
+        timezone => Ok(timezone),
+    }
+}
+
+/// Make sure a `strftime`-like format is supported by chrono, which panics on invalid formats
+/// when used.
+fn parse_time_format(s: &str) -> anyhow::Result<String> {
+    if StrftimeItems::new(s).any(|item| item == Item::Error) {
+        Err(anyhow::anyhow!(format!(
+            "Invalid time format value: '{}'",
+            s
+        )))
+    } else {
+        Ok(s.to_string())
+    }
+}
+
+/// The command line arguments, preceded by the ones from the `BUNYAN_OPTS` environment variable,
+/// split like a shell would.
+fn args() -> anyhow::Result<Vec<OsString>> {
+    let mut args: Vec<OsString> = std::env::args_os().collect();
+    if let Some(opts) = std::env::var_os("BUNYAN_OPTS") {
+        let opts = opts.to_string_lossy();
+        let defaults = shlex::split(&opts)
+            .ok_or_else(|| anyhow::anyhow!(format!("Invalid BUNYAN_OPTS value: '{}'", opts)))?;
+        // The first argument is the name of the binary.
+        let at = args.len().min(1);
+        args.splice(at..at, defaults.into_iter().map(OsString::from));
+    }
+    Ok(args)
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse_from(args()?);
+
+    // Precedence, from highest to lowest: `--color`, `--no-color`, the `NO_COLOR` environment
+    // variable (see https://no-color.org), TTY detection.
+    let color = if cli.color || (cli.color_escape == ColorEscape::Visible && !cli.no_color) {
+        true
+    } else if cli.no_color || std::env::var_os("NO_COLOR").is_some() {
+        false
+    } else {
+        cli.output_file.is_none() && std::io::stdout().is_terminal()
+    };
+
+    let levels = match &cli.level_map {
+        Some(path) => std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read the level map {}", path.display()))?
+            .parse::<LevelMap>()?,
+        None => LevelMap::default(),
+    };
+
+    let mut field_renderers = FieldRenderers::default();
+    for (key, style) in cli.render {
+        field_renderers.register_style(key, style);
+    }
+
+    let mut theme = cli.color_theme;
+    if let Some(ColorChoice(color)) = cli.msg_color {
+        theme.message.color = color;
+    }
+    if let Some(ColorChoice(color)) = cli.mark_raw_color {
+        theme.raw_marker.color = color;
+    }
+    if let Some(style) = cli.fatal_style {
+        theme.fatal = style.paint();
+    }
+
+    let options = FormatOptions {
+        input_format: cli.input_format,
+        format: cli.output,
+        columns: cli.columns,
+        time: if cli.utc { TimeMode::Utc } else { cli.time },
+        time_precision: cli.time_precision,
+        time_format: cli.time_format,
+        raw_time: cli.raw_time,
+        assume_tz: cli.assume_tz,
+        level: cli.level.0,
+        max_level: cli.level_max.map(|level| level.0),
+        levels,
+        default_level: cli.default_level.0,
+        level_field: cli.level_field,
+        level_scheme: cli.level_scheme,
+        msg_field: cli.msg_field,
+        level_width: cli.level_width,
+        align: cli.align,
+        condition: cli.condition,
+        hostnames: cli.hostnames,
+        grep: cli.grep,
+        invert_grep: cli.invert_grep,
+        since: cli.since.map(|bound| bound.0),
+        until: cli.until.map(|bound| bound.0),
+        assume_sorted: cli.assume_sorted,
+        sort_window: if cli.sort {
+            Some(cli.sort_window)
+        } else {
+            None
+        },
+        max_count: cli.count.map(|count| count as usize),
+        before_context: cli.before_context,
+        after_context: cli.after_context,
+        pids: cli.pids,
+        names: cli.names,
+        name_substring: cli.name_substring,
+        line_numbers: cli.line_numbers,
+        quiet: cli.quiet,
+        validate: cli.validate,
+        strict: cli.strict || cli.validate,
+        raw: cli.raw,
+        raw_marker: cli.mark_raw,
+        multiline: cli.multiline,
+        lenient: cli.lenient,
+        debug_parse: cli.debug_parse,
+        group_by: cli.group_by,
+        theme,
+        highlight_level: cli.highlight_level,
+        extract: cli.extract,
+        fields: cli.fields,
+        exclude_fields: cli.exclude_fields,
+        flatten: cli.flatten,
+        color_values: cli.color_values,
+        field_renderers,
+        extras_separator: cli.extras_separator,
+        extras_wrap: cli.extras_wrap,
+        quote_style: cli.quote_style,
+        show_name: !cli.no_name,
+        show_pid: !cli.no_pid,
+        show_hostname: !cli.no_host,
+        show_version: cli.show_version,
+        slow_threshold: cli.slow_threshold,
+        details_threshold: if cli.no_details {
+            None
+        } else {
+            Some(cli.details_threshold)
+        },
+        truncate_values: cli.truncate_values,
+        json_indent: cli.json_indent,
+        pretty_req_body: cli.pretty_req_body,
+        max_width: match cli.max_width {
+            Some(0) => None,
+            Some(width) => Some(width),
+            // The terminal has nothing to do with the file.
+            None if cli.output_file.is_some() => None,
+            None => terminal_size::terminal_size().map(|(width, _)| width.0 as usize),
+        },
+        color,
+        color_depth: cli.color_depth.unwrap_or_else(ColorDepth::from_env),
+        color_escape: cli.color_escape,
+    };
+    let mut output: Box<dyn Write> = match &cli.output_file {
+        Some(path) => {
+            let file = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .append(cli.append)
+                .truncate(!cli.append)
+                .open(path)
+                .with_context(|| format!("Failed to open the output file {}", path.display()))?;
+            Box::new(BufWriter::new(file))
+        }
+        None => Box::new(BufWriter::new(std::io::stdout().lock())),
+    };
+    catch_interrupts();
+    let mut stats = Stats::default();
+    let processed = if let Some(address) = &cli.listen {
+        listen(address.as_str(), &mut output, &options, &mut stats).map(|()| true)
+    } else if cli.files.is_empty() {
+        process_stdin(&mut output, &options, &mut stats).map(|()| true)
+    } else {
+        let process = if cli.follow {
+            follow_files
+        } else if cli.merge {
+            merge_files
+        } else {
+            process_files
+        };
+        process(&cli.files, &mut output, &options, &mut stats)
+    };
+    let processed = processed.and_then(|all_opened| {
+        // `exit` below would not flush it.
+        output.flush()?;
+        Ok(all_opened)
+    });
+    let all_opened = match processed {
+        Ok(all_opened) => all_opened,
+        // Whoever reads the output is gone (e.g. `bunyan app.log | head`): nothing left to do.
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    if cli.stats {
+        eprint!("{}", stats);
+    }
+    // Like the shell would report for a process killed by SIGINT.
+    if interrupted() {
+        std::process::exit(130);
+    }
+    if !all_opened {
+        std::process::exit(2);
+    }
+    if options.validate && stats.non_records > 0 {
+        std::process::exit(1);
+    }
+    if options.quiet && options.has_filters() && stats.matched == 0 {
+        std::process::exit(1);
+    }
+    Ok(())
 }
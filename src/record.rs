@@ -1,144 +1,1583 @@
-use crate::{Format, NamedLogLevel};
-use chrono::{DateTime, Local, SecondsFormat, Utc};
-use colored::{Colorize, CustomColor};
+use crate::{
+    Align, ColorEscape, Column, FieldRendering, Format, FormatError, FormatOptions, FormatState,
+    InputFormat, NamedLogLevel, Paint, QuoteStyle, TimeMode,
+};
+use chrono::{DateTime, Local, TimeDelta, TimeZone, Utc};
 use itertools::Itertools;
+use serde::de::{Deserializer, IgnoredAny, MapAccess, Visitor};
 use serde::Serialize;
 use serde_json::ser::PrettyFormatter;
+use serde_json::value::RawValue;
 use serde_json::Serializer;
 use std::borrow::Cow;
+use std::collections::HashSet;
 use std::convert::TryFrom;
+use std::fmt::Write;
 
-#[derive(serde::Deserialize)]
+/// The version of the bunyan log format, see `LogRecord::v`.
+pub const LOG_FORMAT_VERSION: u8 = 0;
+
+/// A bunyan log record.
+///
+/// Should a key show up more than once in the JSON object, the record is not accepted if it is
+/// one of the fields below; extra fields keep their last value, at the position of their
+/// first occurrence. In strict mode, objects with duplicate keys are never accepted as records
+/// (see `ParsedLine::parse`).
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
 pub struct LogRecord<'a> {
     /// This is the bunyan log format version. The log version is a single integer0
     /// It is meant to be 0 until version "1.0.0" of `node-bunyan` is released.
     /// Thereafter, starting with 1, this will be incremented if there is any backward incompatible
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub v: Option<u8>,
     /// change to the log record format.
     /// See `LogLevel`
+    ///
+    /// Records without a level are at `FormatOptions::default_level`, unless in strict mode.
+    /// It is read from `FormatOptions::level_field` instead, if the record has it.
+    #[serde(
+        default = "numeric_or_named_level::default",
+        deserialize_with = "numeric_or_named_level::deserialize"
+    )]
     pub level: u8,
     /// The name of the logger that produced the log record.
-    pub name: Option<&'a str>,
+    #[serde(borrow, skip_serializing_if = "Option::is_none")]
+    pub name: Option<Cow<'a, str>>,
     /// The hostname of the machine that produced the log record.
-    pub hostname: Option<&'a str>,
+    #[serde(borrow, skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<Cow<'a, str>>,
     /// The pid of the process that produced the log record.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub pid: Option<u32>,
     /// The time of the event captured by the log in [ISO 8601 extended format](http://en.wikipedia.org/wiki/ISO_8601).
     /// is8601 for bunyan, timestamp for pino
     #[serde(with = "iso8601_or_timestamp")]
     pub time: DateTime<Utc>,
+    /// The `time` field as it was logged, e.g. `2012-02-08T23:56:52.856123+01:00`, if
+    /// `FormatOptions::raw_time` is set.
+    #[serde(skip)]
+    pub raw_time: Option<Cow<'a, str>>,
     /// Log message.
-    #[serde(rename = "msg")]
+    ///
+    /// Messages that are not strings (e.g. numbers or objects) are stored as JSON.
+    /// It is read from `FormatOptions::msg_field` instead, if the record has it, or from the
+    /// `message` field of records without a `msg`.
+    #[serde(
+        rename = "msg",
+        default,
+        deserialize_with = "string_or_json::deserialize"
+    )]
     pub message: Cow<'a, str>,
     /// Any extra contextual piece of information in the log record.
     #[serde(flatten)]
     pub extras: serde_json::Map<String, serde_json::Value>,
 }
 
-fn gray() -> CustomColor {
-    CustomColor::new(128, 128, 128)
+/// The interpretation of a single line of input.
+pub enum ParsedLine<'a> {
+    /// A bunyan log record.
+    Record(LogRecord<'a>),
+    /// Valid JSON, but not a bunyan log record (e.g. `level` or `time` are missing).
+    Json,
+    /// Not JSON at all.
+    Text,
+}
+
+impl<'a> ParsedLine<'a> {
+    /// Interpret a line of input.
+    ///
+    /// In strict mode, objects with duplicate top-level keys or without a level are malformed:
+    /// they are not records. So are objects whose `FormatOptions::level_field` is not a level.
+    /// Objects without a message, be it `FormatOptions::msg_field`, `msg` or `message`, are
+    /// never records.
+    pub fn parse(line: &'a str, options: &FormatOptions) -> Self {
+        match parse_record(line, options) {
+            Ok(record) => ParsedLine::Record(record),
+            Err(_) if serde_json::from_str::<IgnoredAny>(line).is_ok() => ParsedLine::Json,
+            Err(_) => ParsedLine::Text,
+        }
+    }
+}
+
+/// Why a line that looks like a record, i.e. a JSON object, is not one, if it is not, see
+/// `FormattedLine::ParseError`.
+pub(crate) fn parse_error(line: &str, options: &FormatOptions) -> Option<serde_json::Error> {
+    if !line.trim_start().starts_with('{') {
+        return None;
+    }
+    parse_record(line, options).err()
+}
+
+/// Like `ParsedLine::parse`, with the reason why lines are not records, e.g. the position of
+/// a syntax error.
+pub(crate) fn parse_record<'a>(
+    line: &'a str,
+    options: &FormatOptions,
+) -> serde_json::Result<LogRecord<'a>> {
+    use serde::de::Error;
+
+    let mut record = match options.input_format {
+        InputFormat::Auto | InputFormat::Bunyan | InputFormat::Pino => {
+            serde_json::from_str::<LogRecord>(line)?
+        }
+        InputFormat::Gelf => serde_json::from_str::<GelfRecord>(line).map(LogRecord::from)?,
+    };
+    if options.strict && has_duplicate_keys(line) {
+        return Err(Error::custom("duplicate top-level keys"));
+    }
+    match pinned_time(line, options.input_format) {
+        PinnedTime::Any => {}
+        PinnedTime::Time(time) => record.time = time,
+        PinnedTime::Mismatch => {
+            return Err(Error::custom(
+                "the type of `time` does not match the input format",
+            ))
+        }
+    }
+    // Fields under other names are extra fields as far as serde is concerned.
+    let custom_message = options
+        .msg_field
+        .as_deref()
+        .and_then(|field| record.extras.shift_remove(field));
+    // Missing messages are parsed as empty ones: the `msg` field has to be looked for again.
+    let message = match custom_message {
+        Some(message) => Some(message),
+        None if options.input_format == InputFormat::Gelf
+            || !record.message.is_empty()
+            || has_message(line) =>
+        {
+            None
+        }
+        None => match record.extras.shift_remove("message") {
+            Some(message) => Some(message),
+            None => return Err(Error::missing_field("msg")),
+        },
+    };
+    if let Some(message) = message {
+        record.message = string_or_json::deserialize(message)?;
+    }
+    // A level under another name is an extra field as far as serde is concerned.
+    let mut has_renamed_level = false;
+    if let Some(level) = options
+        .level_field
+        .as_deref()
+        .and_then(|field| record.extras.shift_remove(field))
+    {
+        record.level = numeric_or_named_level::deserialize(level)?;
+        has_renamed_level = true;
+    }
+    // GELF levels are syslog severities already mapped to canonical levels.
+    if options.input_format != InputFormat::Gelf {
+        record.level = options.level_scheme.interpret(record.level);
+    }
+    // Records without a level are parsed at the info level: the `level` field has to be
+    // looked for again.
+    let has_level =
+        || has_renamed_level || options.input_format == InputFormat::Gelf || has_level(line);
+    if options.strict && !has_level() {
+        return Err(Error::missing_field("level"));
+    }
+    if options.default_level != numeric_or_named_level::default() && !has_level() {
+        record.level = options.default_level;
+    }
+    if options.raw_time {
+        record.raw_time = raw_time(line, options.input_format);
+    }
+    if options.assume_tz == TimeMode::Local {
+        if let Some(time) = local_naive_time(line) {
+            record.time = time;
+        }
+    }
+    Ok(record)
 }
 
 impl LogRecord<'_> {
-    pub fn format(&self, _format: Format, utc: bool) -> String {
-        let level = format_level(self.level);
-        let rfc3339 = if utc {
-            self.time.to_rfc3339_opts(SecondsFormat::Millis, true)
-        } else {
-            self.time
+    /// The value at `pointer` (e.g. `/req/headers/host`, see RFC 6901) in the record, as
+    /// serialized to JSON.
+    pub fn extract(&self, pointer: &str) -> Option<serde_json::Value> {
+        serde_json::to_value(self)
+            .ok()?
+            .pointer_mut(pointer)
+            .map(serde_json::Value::take)
+    }
+
+    /// The record, independent of the line it was parsed from, e.g. to be kept after reading
+    /// the next line.
+    pub fn into_owned(self) -> LogRecord<'static> {
+        LogRecord {
+            v: self.v,
+            level: self.level,
+            name: self.name.map(|name| Cow::Owned(name.into_owned())),
+            hostname: self
+                .hostname
+                .map(|hostname| Cow::Owned(hostname.into_owned())),
+            pid: self.pid,
+            time: self.time,
+            raw_time: self
+                .raw_time
+                .map(|raw_time| Cow::Owned(raw_time.into_owned())),
+            message: Cow::Owned(self.message.into_owned()),
+            extras: self.extras,
+        }
+    }
+
+    /// Render the record using the specified output format.
+    pub fn format(&self, options: &FormatOptions) -> Result<String, FormatError> {
+        let mut formatted = String::new();
+        self.format_into(&mut formatted, options)?;
+        Ok(formatted)
+    }
+
+    /// Like `format`, appending the rendered record to `buffer`: reusing the same buffer for
+    /// all records saves most of the allocations of rendering them.
+    pub fn format_into(
+        &self,
+        buffer: &mut String,
+        options: &FormatOptions,
+    ) -> Result<(), FormatError> {
+        self.format_with_state(buffer, options, &mut FormatState::new())
+    }
+
+    /// Like `format_into`, after the records rendered before with `state`.
+    pub(crate) fn format_with_state(
+        &self,
+        buffer: &mut String,
+        options: &FormatOptions,
+        state: &mut FormatState,
+    ) -> Result<(), FormatError> {
+        let mut extras = options.select_extras(&self.extras);
+        if let Some(v) = self
+            .v
+            .filter(|v| options.show_version && *v != LOG_FORMAT_VERSION)
+        {
+            extras.to_mut().insert("v".into(), v.into());
+        }
+        if options.flatten {
+            extras = Cow::Owned(flatten(&extras));
+        }
+        // Where the prefix of the record starts, see `align`.
+        let start = buffer.len();
+        // Writing to a `String` cannot fail: `write!` results are unwrapped.
+        match options.format {
+            Format::Long => {
+                let time = self.format_full_time(options, state);
+                let origin = self.format_origin(options);
+                if options.highlight_level {
+                    // The whole prefix is painted at once, e.g. for FATAL to be reversed
+                    // without gaps.
+                    let (level, paint) = level_name_and_paint(self.level, options);
+                    let prefix = format!("[{}] {}{}", time, level, origin);
+                    paint_into(buffer, &prefix, paint, options);
+                } else {
+                    write!(buffer, "[{}] ", time).unwrap();
+                    write_level(buffer, self.level, options);
+                    buffer.push_str(&origin);
+                }
+                write_src_header(buffer, &extras, options);
+                buffer.push_str(": ");
+                align(buffer, start, options, state);
+                self.write_message(buffer, options);
+                write_extras(buffer, &extras, options);
+            }
+            Format::Short => {
+                let time = match (&self.raw_time, &options.time_format) {
+                    (Some(raw_time), _) => raw_time.to_string(),
+                    (None, Some(time_format)) => self.format_time(time_format, options, state),
+                    (None, None) => {
+                        let time_format =
+                            format!("%H:%M:%S{}", options.time_precision.strftime_fraction());
+                        self.format_time(&time_format, options, state)
+                    }
+                };
+                if options.highlight_level {
+                    let (level, paint) = level_name_and_paint(self.level, options);
+                    paint_into(buffer, &format!("{} {}", time, level), paint, options);
+                } else {
+                    write!(buffer, "{} ", time).unwrap();
+                    write_level(buffer, self.level, options);
+                }
+                write_src_header(buffer, &extras, options);
+                buffer.push_str(": ");
+                align(buffer, start, options, state);
+                self.write_message(buffer, options);
+                write_extras(buffer, &extras, options);
+            }
+            Format::Json(0) | Format::Bunyan => {
+                let json = serde_json::to_string(self).map_err(FormatError::Serialization)?;
+                writeln!(buffer, "{}", json).unwrap()
+            }
+            Format::Json(indent) => {
+                let json = json_to_indented_string(self, &" ".repeat(indent))
+                    .map_err(FormatError::Serialization)?;
+                writeln!(buffer, "{}", json).unwrap()
+            }
+            Format::Inspect => writeln!(buffer, "{:#?}", self).unwrap(),
+            Format::Logfmt => self.write_logfmt(buffer, &extras, options, state),
+            Format::Simple => {
+                write_level(buffer, self.level, options);
+                buffer.push_str(": ");
+                align(buffer, start, options, state);
+                self.write_message(buffer, options);
+                buffer.push('\n');
+            }
+            Format::Table => self.write_table_row(buffer, options, state),
+        }
+        Ok(())
+    }
+
+    /// The time of the record as shown in the long format.
+    fn format_full_time(&self, options: &FormatOptions, state: &mut FormatState) -> String {
+        match (&self.raw_time, &options.time_format) {
+            (Some(raw_time), _) => raw_time.to_string(),
+            (None, Some(time_format)) => self.format_time(time_format, options, state),
+            (None, None) => self.format_rfc3339(options, state),
+        }
+    }
+
+    /// Where the record comes from, as shown after the level in the long format:
+    /// `: name/pid on hostname`, without the parts hidden by `FormatOptions::show_name`,
+    /// `show_pid` and `show_hostname`.
+    fn format_origin(&self, options: &FormatOptions) -> String {
+        let name = self.name.as_deref().unwrap_or_default();
+        let pid = self.pid.unwrap_or(0);
+        let mut origin = match (options.show_name, options.show_pid) {
+            (true, true) => format!(": {}/{}", name, pid),
+            (true, false) => format!(": {}", name),
+            (false, true) => format!(": {}", pid),
+            (false, false) => String::new(),
+        };
+        if options.show_hostname {
+            let hostname = self.hostname.as_deref().unwrap_or_default();
+            if origin.is_empty() {
+                write!(origin, ": on {}", hostname).unwrap();
+            } else {
+                write!(origin, " on {}", hostname).unwrap();
+            }
+        }
+        origin
+    }
+
+    /// Render the record as a logfmt line: `time`, `level`, `name`, `hostname`, `pid` and
+    /// `msg`, followed by the extra fields.
+    fn write_logfmt(
+        &self,
+        buffer: &mut String,
+        extras: &serde_json::Map<String, serde_json::Value>,
+        options: &FormatOptions,
+        state: &mut FormatState,
+    ) {
+        let style = options.quote_style;
+        let time = self.format_rfc3339(options, state);
+        write!(buffer, "time={} level=", time).unwrap();
+        match options.levels.get(self.level) {
+            Some(custom) => write_logfmt_value(buffer, &custom.name, style),
+            None => match NamedLogLevel::try_from(self.level) {
+                Ok(level) => buffer.push_str(level.name()),
+                Err(_) => write!(buffer, "{}", self.level).unwrap(),
+            },
+        }
+        if let Some(name) = &self.name {
+            buffer.push_str(" name=");
+            write_logfmt_value(buffer, name, style);
+        }
+        if let Some(hostname) = &self.hostname {
+            buffer.push_str(" hostname=");
+            write_logfmt_value(buffer, hostname, style);
+        }
+        if let Some(pid) = self.pid {
+            write!(buffer, " pid={}", pid).unwrap();
+        }
+        buffer.push_str(" msg=");
+        write_logfmt_value(buffer, &self.message, style);
+        for (key, value) in extras {
+            write!(buffer, " {}=", key).unwrap();
+            match value {
+                serde_json::Value::String(s) => write_logfmt_value(buffer, s, style),
+                // Not strings: quoted only if needed, e.g. objects.
+                value => write_logfmt_value(buffer, &value.to_string(), QuoteStyle::Minimal),
+            }
+        }
+        buffer.push('\n');
+    }
+
+    /// Render the record as a row of `FormatOptions::columns`, separated by two spaces.
+    ///
+    /// Cells are padded to the width of their column, numbers to the right: the last one is
+    /// not, nor is the row ended with spaces. Messages are cut at their first line, and to fit
+    /// within `FormatOptions::max_width` along with the other columns.
+    fn write_table_row(
+        &self,
+        buffer: &mut String,
+        options: &FormatOptions,
+        state: &mut FormatState,
+    ) {
+        const SEPARATOR: &str = "  ";
+
+        let columns = &options.columns;
+        let count = columns.iter().count();
+        let row_start = buffer.len();
+        for (index, column) in columns.iter().enumerate() {
+            if index > 0 {
+                buffer.push_str(SEPARATOR);
+            }
+            let cell_start = buffer.len();
+            match column {
+                Column::Time => buffer.push_str(&self.format_full_time(options, state)),
+                Column::Level => write_level(buffer, self.level, options),
+                Column::Name => buffer.push_str(self.name.as_deref().unwrap_or_default()),
+                Column::Hostname => buffer.push_str(self.hostname.as_deref().unwrap_or_default()),
+                Column::Pid => {
+                    if let Some(pid) = self.pid {
+                        write!(buffer, "{}", pid).unwrap();
+                    }
+                }
+                Column::Msg => {
+                    let following: usize = (index + 1..count)
+                        .map(|index| SEPARATOR.len() + state.column_width(index))
+                        .sum();
+                    let limit = options.max_width.map(|max_width| {
+                        max_width.saturating_sub(visible_width(&buffer[row_start..]) + following)
+                    });
+                    let message = cut(&self.message, limit);
+                    write_highlighted(buffer, &message, options);
+                }
+                Column::Extra(path) => {
+                    let mut keys = path.split('.');
+                    let value = keys
+                        .next()
+                        .and_then(|key| self.extras.get(key))
+                        .and_then(|value| keys.try_fold(value, |value, key| value.get(key)));
+                    match value {
+                        Some(serde_json::Value::String(s)) => buffer.push_str(s),
+                        Some(value) => write!(buffer, "{}", value).unwrap(),
+                        None => {}
+                    }
+                }
+            }
+            let width = visible_width(&buffer[cell_start..]);
+            let padding = state.widen_column(index, width) - width;
+            if index + 1 == count {
+                continue;
+            }
+            if *column == Column::Pid {
+                buffer.insert_str(cell_start, &" ".repeat(padding));
+            } else {
+                buffer.extend(std::iter::repeat_n(' ', padding));
+            }
+        }
+        // Empty cells at the end of the row would leave it with trailing spaces.
+        let end = row_start.max(buffer.trim_end_matches(' ').len());
+        buffer.truncate(end);
+        buffer.push('\n');
+    }
+
+    /// The message, highlighting the parts matching `FormatOptions::grep`, if any.
+    fn write_message(&self, buffer: &mut String, options: &FormatOptions) {
+        write_highlighted(buffer, &self.message, options);
+    }
+
+    fn format_rfc3339(&self, options: &FormatOptions, state: &mut FormatState) -> String {
+        let seconds_format = options.time_precision.seconds_format();
+        match options.time {
+            TimeMode::Utc => self.time.to_rfc3339_opts(seconds_format, true),
+            TimeMode::Local => self
+                .time
                 .with_timezone(&Local)
-                .to_rfc3339_opts(SecondsFormat::Millis, true)
+                .to_rfc3339_opts(seconds_format, true),
+            TimeMode::Relative => self.format_relative(options, state),
+        }
+    }
+
+    fn format_time(&self, fmt: &str, options: &FormatOptions, state: &mut FormatState) -> String {
+        match options.time {
+            TimeMode::Utc => self.time.format(fmt).to_string(),
+            TimeMode::Local => self.time.with_timezone(&Local).format(fmt).to_string(),
+            TimeMode::Relative => self.format_relative(options, state),
+        }
+    }
+
+    /// The time elapsed since the first record, e.g. `+1.234`, see `TimeMode::Relative`.
+    fn format_relative(&self, options: &FormatOptions, state: &mut FormatState) -> String {
+        let elapsed = state.elapsed(self.time);
+        let sign = if elapsed < TimeDelta::zero() {
+            '-'
+        } else {
+            '+'
         };
-        let formatted = format!(
-            "[{}] {} ({}): {}{}",
-            rfc3339,
-            level,
-            self.pid.unwrap_or(0),
-            self.message.cyan(),
-            format_extras(&self.extras)
-        );
-        formatted
+        let elapsed = elapsed.abs();
+        let digits = options.time_precision.digits();
+        if digits == 0 {
+            return format!("{}{}", sign, elapsed.num_seconds());
+        }
+        let fraction = format!("{:09}", elapsed.subsec_nanos());
+        format!("{}{}.{}", sign, elapsed.num_seconds(), &fraction[..digits])
+    }
+}
+
+/// A [GELF](https://go2docs.graylog.org/current/getting_in_log_data/gelf.html) message.
+#[derive(serde::Deserialize)]
+struct GelfRecord<'a> {
+    #[serde(borrow)]
+    host: Option<Cow<'a, str>>,
+    #[serde(borrow)]
+    short_message: Cow<'a, str>,
+    /// Seconds since the epoch, possibly fractional.
+    #[serde(with = "iso8601_or_timestamp")]
+    timestamp: DateTime<Utc>,
+    /// A syslog severity, 1 (alert) if missing.
+    level: Option<u8>,
+    /// The version of the GELF format.
+    #[serde(rename = "version")]
+    _version: Option<IgnoredAny>,
+    /// Additional fields, whose names start with `_`, as well as non-standard ones.
+    #[serde(flatten)]
+    extras: serde_json::Map<String, serde_json::Value>,
+}
+
+impl<'a> From<GelfRecord<'a>> for LogRecord<'a> {
+    fn from(gelf: GelfRecord<'a>) -> Self {
+        LogRecord {
+            v: None,
+            level: NamedLogLevel::from_syslog(gelf.level.unwrap_or(1)) as u8,
+            name: None,
+            hostname: gelf.host,
+            pid: None,
+            time: gelf.timestamp,
+            raw_time: None,
+            message: gelf.short_message,
+            extras: gelf
+                .extras
+                .into_iter()
+                .map(|(key, value)| match key.strip_prefix('_') {
+                    Some(stripped) => (stripped.to_owned(), value),
+                    None => (key, value),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The time of a record whose `time` is a timestamp without a timezone, in the local timezone.
+///
+/// Records are parsed assuming such timestamps are in UTC: the `time` field has to be read
+/// again.
+fn local_naive_time(line: &str) -> Option<DateTime<Utc>> {
+    #[derive(serde::Deserialize)]
+    struct Time<'a> {
+        #[serde(borrow)]
+        time: Cow<'a, str>,
+    }
+
+    let time = serde_json::from_str::<Time>(line).ok()?.time;
+    let naive = iso8601_or_timestamp::parse_naive(&time)?;
+    Some(
+        Local
+            .from_local_datetime(&naive)
+            .earliest()?
+            .with_timezone(&Utc),
+    )
+}
+
+/// The time field of a record, as it was logged.
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum RawTime<'a> {
+    String(#[serde(borrow)] Cow<'a, str>),
+    Number(serde_json::Number),
+}
+
+/// The time field of the record in `line`, as it was logged: strings as they are, timestamps
+/// as their JSON numbers were written (e.g. `1328741812.8560`).
+fn raw_time(line: &str, input_format: InputFormat) -> Option<Cow<'_, str>> {
+    #[derive(serde::Deserialize)]
+    struct Time<'a> {
+        #[serde(borrow)]
+        time: &'a RawValue,
+    }
+    #[derive(serde::Deserialize)]
+    struct Timestamp<'a> {
+        #[serde(borrow)]
+        timestamp: &'a RawValue,
+    }
+
+    let time = match input_format {
+        InputFormat::Auto | InputFormat::Bunyan | InputFormat::Pino => {
+            serde_json::from_str::<Time>(line).ok()?.time
+        }
+        InputFormat::Gelf => serde_json::from_str::<Timestamp>(line).ok()?.timestamp,
+    };
+    match serde_json::from_str::<RawTime>(time.get()).ok()? {
+        RawTime::String(s) => Some(s),
+        RawTime::Number(_) => Some(Cow::Borrowed(time.get())),
+    }
+}
+
+/// The `time` field of the record in `line`, as it was logged.
+fn logged_time(line: &str) -> Option<RawTime<'_>> {
+    #[derive(serde::Deserialize)]
+    struct Time<'a> {
+        #[serde(borrow)]
+        time: RawTime<'a>,
+    }
+
+    serde_json::from_str::<Time>(line).ok().map(|t| t.time)
+}
+
+/// What an input format requires of the time of a record.
+enum PinnedTime {
+    /// Any representation, as parsed.
+    Any,
+    /// This time, rather than the one parsed.
+    Time(DateTime<Utc>),
+    /// The time of the record is represented in another way: it is not a record.
+    Mismatch,
+}
+
+/// How the time of the record in `line` is interpreted by `input_format`: bunyan records have
+/// ISO 8601 strings, pino records milliseconds since the epoch, even when they would be taken
+/// for seconds otherwise.
+fn pinned_time(line: &str, input_format: InputFormat) -> PinnedTime {
+    // Only pinned formats need the time to be read again.
+    if matches!(input_format, InputFormat::Auto | InputFormat::Gelf) {
+        return PinnedTime::Any;
+    }
+    match (input_format, logged_time(line)) {
+        (InputFormat::Bunyan, Some(RawTime::String(_))) => PinnedTime::Any,
+        (InputFormat::Pino, Some(RawTime::Number(n))) => {
+            match n
+                .as_i64()
+                .and_then(|ms| Utc.timestamp_millis_opt(ms).single())
+            {
+                Some(time) => PinnedTime::Time(time),
+                None => PinnedTime::Mismatch,
+            }
+        }
+        _ => PinnedTime::Mismatch,
+    }
+}
+
+/// Whether the JSON object in `line` has a `level` field.
+fn has_level(line: &str) -> bool {
+    #[derive(serde::Deserialize)]
+    struct Level {
+        level: Option<IgnoredAny>,
+    }
+
+    serde_json::from_str::<Level>(line).is_ok_and(|record| record.level.is_some())
+}
+
+/// Whether the JSON object in `line` has a `msg` field.
+fn has_message(line: &str) -> bool {
+    #[derive(serde::Deserialize)]
+    struct Message {
+        msg: Option<IgnoredAny>,
+    }
+
+    serde_json::from_str::<Message>(line).is_ok_and(|record| record.msg.is_some())
+}
+
+/// Whether the JSON object in `line` has the same key more than once.
+fn has_duplicate_keys(line: &str) -> bool {
+    struct KeysVisitor;
+
+    impl<'de> Visitor<'de> for KeysVisitor {
+        type Value = bool;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.write_str("a JSON object")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<bool, A::Error> {
+            let mut keys = HashSet::new();
+            let mut duplicates = false;
+            while let Some(key) = map.next_key::<String>()? {
+                map.next_value::<IgnoredAny>()?;
+                duplicates |= !keys.insert(key);
+            }
+            Ok(duplicates)
+        }
+    }
+
+    serde_json::Deserializer::from_str(line)
+        .deserialize_map(KeysVisitor)
+        .unwrap_or(false)
+}
+
+/// The source location of the log call, ` (file:line in function)`, if it was recorded.
+fn write_src_header(
+    buffer: &mut String,
+    extras: &serde_json::Map<String, serde_json::Value>,
+    options: &FormatOptions,
+) {
+    if let Some(src) = extras.get("src").and_then(format_src) {
+        paint_into(buffer, &format!(" ({})", src), options.theme.src, options);
+    }
+}
+
+/// Append `message`, highlighting the parts matching `FormatOptions::grep`, if any.
+fn write_highlighted(buffer: &mut String, message: &str, options: &FormatOptions) {
+    let grep = match &options.grep {
+        Some(grep) if !options.invert_grep => grep,
+        _ => return paint_into(buffer, message, options.theme.message, options),
+    };
+    let mut last_end = 0;
+    // Empty segments are skipped, or they would be rendered as empty escape sequences.
+    for m in grep.find_iter(message).filter(|m| !m.is_empty()) {
+        if m.start() > last_end {
+            let before = &message[last_end..m.start()];
+            paint_into(buffer, before, options.theme.message, options);
+        }
+        paint_into(buffer, m.as_str(), options.theme.highlight, options);
+        last_end = m.end();
+    }
+    if last_end < message.len() {
+        let after = &message[last_end..];
+        paint_into(buffer, after, options.theme.message, options);
+    }
+}
+
+/// Render `s` with the specified paint, if the output is colorized.
+fn paint(s: &str, paint: Paint, options: &FormatOptions) -> String {
+    let mut painted = String::new();
+    paint_into(&mut painted, s, paint, options);
+    painted
+}
+
+/// Like `paint`, appending to `buffer`.
+pub(crate) fn paint_into(buffer: &mut String, s: &str, paint: Paint, options: &FormatOptions) {
+    if options.color {
+        match options.color_escape {
+            ColorEscape::Ansi => paint.write(buffer, s, options.color_depth),
+            ColorEscape::Visible => paint.write_visible(buffer, s),
+        }
+    } else {
+        buffer.push_str(s);
     }
 }
 
-pub fn format_level(level: u8) -> String {
-    if let Ok(level) = NamedLogLevel::try_from(level) {
-        match level {
-            // Making sure all levels are 5 characters
-            NamedLogLevel::Fatal => "FATAL".reversed(),
-            NamedLogLevel::Error => "ERROR".red(),
-            NamedLogLevel::Warn => " WARN".yellow(),
-            NamedLogLevel::Info => " INFO".green(),
-            NamedLogLevel::Debug => "DEBUG".blue(),
-            NamedLogLevel::Trace => "TRACE".custom_color(gray()),
+pub fn write_level(buffer: &mut String, level: u8, options: &FormatOptions) {
+    let (name, paint) = level_name_and_paint(level, options);
+    paint_into(buffer, &name, paint, options)
+}
+
+/// How a level is rendered: its name, padded to `FormatOptions::level_width` characters, and
+/// its paint.
+fn level_name_and_paint(level: u8, options: &FormatOptions) -> (Cow<'static, str>, Paint) {
+    let width = options.level_width;
+    if let Some(custom) = options.levels.get(level) {
+        // Custom level names are upper-cased and padded like the canonical ones.
+        let name = format!("{:>1$}", custom.name.to_uppercase(), width);
+        (name.into(), custom.color.into())
+    } else if let Ok(level) = NamedLogLevel::try_from(level) {
+        let theme = &options.theme;
+        // Names are padded to 5 characters, the length of the longest ones: narrower widths
+        // are slices of them.
+        let (name, paint) = match level {
+            NamedLogLevel::Fatal => ("FATAL", theme.fatal),
+            NamedLogLevel::Error => ("ERROR", theme.error),
+            NamedLogLevel::Warn => (" WARN", theme.warn),
+            NamedLogLevel::Info => (" INFO", theme.info),
+            NamedLogLevel::Debug => ("DEBUG", theme.debug),
+            NamedLogLevel::Trace => ("TRACE", theme.trace),
+        };
+        if width <= name.len() {
+            let start = name.len() - width.max(name.trim_start().len());
+            (name[start..].into(), paint)
+        } else {
+            (format!("{:>1$}", name, width).into(), paint)
         }
-        .to_string()
     } else {
-        format!("LVL{}", level)
+        let name = format!("{:>1$}", format!("LVL{}", level), width);
+        (name.into(), Paint::default())
     }
 }
 
-pub fn format_extras(extra_fields: &serde_json::Map<String, serde_json::Value>) -> String {
-    let mut details = Vec::new();
-    let mut extras = Vec::new();
+pub fn write_extras(
+    buffer: &mut String,
+    extra_fields: &serde_json::Map<String, serde_json::Value>,
+    options: &FormatOptions,
+) {
+    // The inline extras are rendered on the current line, after the header.
+    let line = &buffer[buffer.rfind('\n').map_or(0, |i| i + 1)..];
+    let mut extras = Extras::new(options, visible_width(line));
     for (key, value) in extra_fields {
-        let stringified = if let serde_json::Value::String(s) = value {
-            // Preserve strings unless they contain whitespaces/are empty
-            // In that case, we want surrounding quotes.
-            if s.contains(' ') || s.is_empty() {
-                format!("\"{}\"", s)
+        let rendering = options
+            .field_renderers
+            .get(key)
+            .and_then(|renderer| renderer(value));
+        match rendering {
+            Some(FieldRendering::Inline { value, paint }) => {
+                extras.push_entry(key, &value, paint);
+                continue;
+            }
+            Some(FieldRendering::Styled { value, paint }) => {
+                let stringified = extras.single_line(&value);
+                extras.push_entry(key, &stringified, paint);
+                continue;
+            }
+            // When everything must be inline, the field is rendered like any other.
+            Some(FieldRendering::Details(value)) if options.details_threshold.is_none() => {
+                extras.push(key, &value);
+                continue;
+            }
+            Some(FieldRendering::Details(value)) => {
+                let value = match value {
+                    serde_json::Value::String(s) => s,
+                    value => extras.json(&value),
+                };
+                let block = format!("{}: {}", extras.key(key), value);
+                extras.push_details(&block);
+                continue;
+            }
+            Some(FieldRendering::Hidden) => continue,
+            None => {}
+        }
+        match (key.as_str(), value) {
+            // Rendered next to the record's header, see `write_src_header`.
+            ("src", src) if format_src(src).is_some() => {}
+            ("latency" | "responseTime" | "duration", serde_json::Value::Number(duration)) => {
+                extras.push_duration(key, duration)
+            }
+            // The special renderings below are blocks in the details: when everything must be
+            // inline, these fields are rendered like any other.
+            (_, _) if options.details_threshold.is_none() => extras.push(key, value),
+            ("err", serde_json::Value::Object(err)) if is_error(err) => extras.push_err(err),
+            ("stack", serde_json::Value::String(stack)) => extras.push_stack(stack),
+            ("req", serde_json::Value::Object(req)) if is_request(req) => {
+                extras.push_req(key, req, "")
+            }
+            ("res", serde_json::Value::Object(res)) if is_response(res) => {
+                extras.push_res(key, res, "")
+            }
+            // Outbound HTTP exchanges, as opposed to the inbound `req`/`res`.
+            ("client_req", serde_json::Value::Object(req)) if is_request(req) => {
+                extras.push_req(key, req, CLIENT_REQUEST_PREFIX)
+            }
+            ("client_res", serde_json::Value::Object(res)) if is_response(res) => {
+                extras.push_res(key, res, CLIENT_RESPONSE_PREFIX)
+            }
+            _ => extras.push(key, value),
+        }
+    }
+    extras.finish(buffer)
+}
+
+/// The rendering of the extra fields of a record, in progress: short values are shown inline
+/// (`key=value`) after the message, long or multi-line values are moved to the details block
+/// below the record.
+struct Extras<'a> {
+    options: &'a FormatOptions,
+    /// The inline extras rendered so far, separated by `FormatOptions::extras_separator`.
+    inline: String,
+    /// The width of the line the inline extras are rendered on, escape sequences excluded.
+    width: usize,
+    /// Whether an inline extra did not fit within `FormatOptions::max_width`: the following
+    /// ones are moved to the details as well, to preserve their order.
+    overflowed: bool,
+    details: Vec<String>,
+}
+
+impl<'a> Extras<'a> {
+    /// `width` is the width of the line before the inline extras, i.e. of the header.
+    fn new(options: &'a FormatOptions, width: usize) -> Self {
+        Extras {
+            options,
+            inline: String::new(),
+            width,
+            overflowed: false,
+            details: Vec::new(),
+        }
+    }
+
+    /// Append the inline extras, the end of the line and the details to `buffer`.
+    fn finish(self, buffer: &mut String) {
+        if !self.inline.is_empty() {
+            if self.options.extras_wrap {
+                write!(buffer, " ({})", self.inline).unwrap();
             } else {
-                s.to_owned()
+                write!(buffer, " {}", self.inline).unwrap();
+            }
+        }
+        buffer.push('\n');
+        if !self.details.is_empty() {
+            let separator = format!("\n{}--\n", " ".repeat(2 * self.options.json_indent));
+            writeln!(buffer, "{}", self.details.iter().join(&separator)).unwrap();
+        }
+    }
+
+    /// Add `key=value` to the inline extras, or to the details if the line would get wider
+    /// than `FormatOptions::max_width`.
+    fn push_entry(&mut self, key: &str, value: &str, paint: Paint) {
+        let separator = &self.options.extras_separator;
+        let mut width = key.chars().count() + 1 + value.chars().count();
+        if self.inline.is_empty() {
+            // ` (` and `)`, or just ` `.
+            width += if self.options.extras_wrap { 3 } else { 1 };
+        } else {
+            width += separator.chars().count();
+        }
+        // Details are out of the question when everything must be inline.
+        if self.options.details_threshold.is_some() {
+            let max_width = self.options.max_width.unwrap_or(usize::MAX);
+            self.overflowed = self.overflowed || self.width.saturating_add(width) > max_width;
+        }
+        if self.overflowed {
+            self.push_details(&format!("{}: {}", self.key(key), value));
+            return;
+        }
+        self.width += width;
+        if !self.inline.is_empty() {
+            self.inline.push_str(separator);
+        }
+        paint_into(&mut self.inline, key, self.options.theme.key, self.options);
+        self.inline.push('=');
+        paint_into(&mut self.inline, value, paint, self.options);
+    }
+
+    /// Generic rendering of an extra field, inline or in the details depending on the length
+    /// of its value (see `FormatOptions::details_threshold`).
+    fn push(&mut self, key: &str, value: &serde_json::Value) {
+        let limit = self.options.truncate_values;
+        let threshold = match self.options.details_threshold {
+            Some(threshold) => threshold,
+            None => {
+                let stringified = self.single_line(value);
+                self.push_inline(key, value, &stringified);
+                return;
             }
+        };
+
+        if let Some(array) = value.as_array().and_then(|array| inline_array(array)) {
+            if array.len() <= threshold && limit.is_none_or(|limit| array.len() <= limit) {
+                self.push_inline(key, value, &array);
+                return;
+            }
+        }
+
+        let raw = match value {
+            serde_json::Value::String(s) => truncate(s, limit),
+            // No need for the pretty-printer, nor to truncate them.
+            serde_json::Value::Bool(_) | serde_json::Value::Null => Cow::Owned(value.to_string()),
+            value => Cow::Owned(truncate(&self.json(value), limit).into_owned()),
+        };
+        let quoted = match (value, self.options.quote_style) {
+            (serde_json::Value::String(_), QuoteStyle::Minimal) => needs_quotes(&raw),
+            (serde_json::Value::String(_), QuoteStyle::Always) => true,
+            _ => false,
+        };
+        let stringified = if quoted {
+            Cow::Owned(serde_json::to_string(raw.as_ref()).unwrap())
         } else {
-            json_to_indented_string(value, "  ")
+            Cow::Borrowed(raw.as_ref())
         };
 
-        if stringified.contains('\n') || stringified.len() > 50 {
-            if let serde_json::Value::String(s) = value {
-                details.push(indent(&format!("{}: {}", key.bold(), s)));
+        if raw.contains('\n') || stringified.len() > threshold {
+            if value.is_string() {
+                self.push_details(&format!("{}: {}", self.key(key), raw));
             } else {
-                details.push(indent(&format!("{}: {}", key.bold(), stringified)));
+                self.push_details(&format!("{}: {}", self.key(key), stringified));
             }
         } else {
-            extras.push(format!("{}={}", key.bold(), stringified));
+            self.push_inline(key, value, &stringified);
         }
     }
-    let formatted_details = if !details.is_empty() {
-        format!("{}\n", details.into_iter().join("\n    --\n"))
-    } else {
-        "".into()
-    };
-    let formatted_extras = if !extras.is_empty() {
-        format!(" ({})", extras.into_iter().join(","))
-    } else {
-        "".into()
+
+    /// A value rendered to fit on the line, quoted (with its newlines escaped) and truncated
+    /// as set by the options.
+    fn single_line<'v>(&self, value: &'v serde_json::Value) -> Cow<'v, str> {
+        let limit = self.options.truncate_values;
+        match value {
+            serde_json::Value::String(s) => {
+                let s = truncate(s, limit);
+                let quoted = match self.options.quote_style {
+                    QuoteStyle::Minimal => needs_quotes(&s) || s.contains('\n'),
+                    QuoteStyle::Always => true,
+                    QuoteStyle::Never => s.contains('\n'),
+                };
+                if quoted {
+                    Cow::Owned(serde_json::to_string(&s).unwrap())
+                } else {
+                    s
+                }
+            }
+            value => Cow::Owned(truncate(&value.to_string(), limit).into_owned()),
+        }
+    }
+
+    /// Add a block to the details.
+    ///
+    /// Blocks are indented by two levels of `FormatOptions::json_indent`, so that they line up
+    /// with the nesting of JSON values.
+    fn push_details(&mut self, block: &str) {
+        self.details
+            .push(indent(block, 2 * self.options.json_indent));
+    }
+
+    /// A JSON value, pretty-printed with `FormatOptions::json_indent`.
+    fn json(&self, value: &serde_json::Value) -> String {
+        json_value_to_indented_string(value, self.options.json_indent)
+    }
+
+    /// The name of an extra field, as rendered.
+    fn key(&self, key: &str) -> String {
+        paint(key, self.options.theme.key, self.options)
+    }
+
+    /// Add `key=value` to the inline extras, coloring scalar values according to their type if
+    /// `FormatOptions::color_values` is set (see `ColorTheme`). Booleans and nulls are always
+    /// colored.
+    fn push_inline(&mut self, key: &str, value: &serde_json::Value, stringified: &str) {
+        let theme = &self.options.theme;
+        let paint = match value {
+            // Always told apart: they are easy to miss otherwise.
+            serde_json::Value::Bool(true) => theme.bool_true,
+            serde_json::Value::Bool(false) => theme.bool_false,
+            serde_json::Value::Null => theme.null,
+            _ if !self.options.color_values => Paint::default(),
+            serde_json::Value::String(_) => theme.string,
+            serde_json::Value::Number(_) => theme.number,
+            serde_json::Value::Array(_) | serde_json::Value::Object(_) => Paint::default(),
+        };
+        self.push_entry(key, stringified, paint);
+    }
+
+    /// Durations are in milliseconds: render them with their unit, highlighted if they are
+    /// above `FormatOptions::slow_threshold`.
+    fn push_duration(&mut self, key: &str, duration: &serde_json::Number) {
+        let formatted = format!("{}ms", duration);
+        let slow = self
+            .options
+            .slow_threshold
+            .is_some_and(|threshold| duration.as_f64().is_some_and(|d| d > threshold));
+        let paint = if slow {
+            self.options.theme.slow
+        } else {
+            Paint::default()
+        };
+        self.push_entry(key, &formatted, paint);
+    }
+
+    /// Render the stack trace of an error as an indented block in the details, while the
+    /// remaining fields of the error (e.g. `code`, `signal`) are rendered as `err.<field>` extras.
+    ///
+    /// Both bunyan's (`name`) and pino's (`type`) ways of recording the kind of error are
+    /// supported: pino's `type` is only rendered if the stack trace does not already show it.
+    fn push_err(&mut self, err: &serde_json::Map<String, serde_json::Value>) {
+        let stack = err["stack"].as_str().unwrap_or_default();
+        // Most runtimes start the stack trace with the error message (e.g. `Error: boom`):
+        // don't print it twice.
+        let first_line = stack.lines().next().unwrap_or_default();
+        match err["message"].as_str() {
+            Some(message) if !first_line.contains(message) => {
+                self.push_details(&format!("{}\n{}", message, stack))
+            }
+            _ => self.push_details(stack),
+        }
+        for (key, value) in err {
+            match (key.as_str(), value) {
+                ("message" | "name" | "stack", _) => {}
+                ("type", serde_json::Value::String(kind))
+                    if first_line.starts_with(kind.as_str()) => {}
+                _ => self.push(&format!("err.{}", key), value),
+            }
+        }
+    }
+
+    /// Render a stack trace logged outside of an `err` field (e.g. by pino, when logging an
+    /// error as the whole record) as an indented block in the details.
+    fn push_stack(&mut self, stack: &str) {
+        self.push_details(stack);
+    }
+
+    /// Render an HTTP request as a classic request block in the details:
+    ///
+    /// ```text
+    /// GET /path HTTP/1.1
+    /// header-name: value
+    ///
+    /// body
+    /// ```
+    ///
+    /// Each line of the block starts with `prefix`.
+    /// The remaining fields of the request are rendered as `<key>.<field>` extras.
+    fn push_req(
+        &mut self,
+        key: &str,
+        req: &serde_json::Map<String, serde_json::Value>,
+        prefix: &str,
+    ) {
+        let mut block = format!(
+            "{} {} HTTP/{}",
+            req["method"].as_str().unwrap_or_default(),
+            req["url"].as_str().unwrap_or_default(),
+            req.get("httpVersion")
+                .and_then(serde_json::Value::as_str)
+                .unwrap_or("1.1")
+        );
+        if let Some(headers) = req.get("headers") {
+            block.push_str(&format_headers(headers));
+        }
+        block.push_str(&format_body(req.get("body"), self.options));
+        if let Some(trailers) = req.get("trailers") {
+            block.push_str(&format_headers(trailers));
+        }
+        self.push_details(&prefix_lines(&block, prefix));
+        for (field, value) in req {
+            if !matches!(
+                field.as_str(),
+                "method" | "url" | "httpVersion" | "headers" | "body" | "trailers"
+            ) {
+                self.push(&format!("{}.{}", key, field), value);
+            }
+        }
+    }
+
+    /// Render an HTTP response as a status line followed by its headers (and body, if any)
+    /// in the details:
+    ///
+    /// ```text
+    /// HTTP/1.1 200 OK
+    /// header-name: value
+    /// ```
+    ///
+    /// If the headers have already been rendered to a string (status line included) they are
+    /// printed as they are.
+    /// Each line of the block starts with `prefix`.
+    /// The remaining fields of the response are rendered as `<key>.<field>` extras.
+    fn push_res(
+        &mut self,
+        key: &str,
+        res: &serde_json::Map<String, serde_json::Value>,
+        prefix: &str,
+    ) {
+        let mut block = match response_headers(res) {
+            Some(serde_json::Value::String(raw)) => raw.trim_end().to_owned(),
+            headers => {
+                let status_code = res["statusCode"].as_u64().unwrap_or_default();
+                format!(
+                    "HTTP/1.1 {} {}{}",
+                    status_code,
+                    status_reason(status_code),
+                    headers.map(format_headers).unwrap_or_default()
+                )
+            }
+        };
+        block.push_str(&format_body(res.get("body"), self.options));
+        if let Some(serde_json::Value::String(trailer)) = res.get("trailer") {
+            block.push_str(&format!("\n{}", trailer));
+        }
+        self.push_details(&prefix_lines(&block, prefix));
+        for (field, value) in res {
+            if !matches!(
+                field.as_str(),
+                "statusCode" | "header" | "headers" | "body" | "trailer"
+            ) {
+                self.push(&format!("{}.{}", key, field), value);
+            }
+        }
+    }
+}
+
+/// Lines of outbound HTTP requests start with this prefix, as in `curl --verbose`.
+const CLIENT_REQUEST_PREFIX: &str = "> ";
+/// Lines of the responses to outbound HTTP requests start with this prefix.
+const CLIENT_RESPONSE_PREFIX: &str = "< ";
+
+/// Start each line of `block` with `prefix`, without trailing whitespace on blank lines.
+fn prefix_lines(block: &str, prefix: &str) -> String {
+    if prefix.is_empty() {
+        return block.to_owned();
+    }
+    block
+        .lines()
+        .map(|line| {
+            if line.is_empty() {
+                prefix.trim_end().to_owned()
+            } else {
+                format!("{}{}", prefix, line)
+            }
+        })
+        .join("\n")
+}
+
+/// Render the `src` field added by bunyan's `src: true` option, either an object like
+/// `{"file": "app.js", "line": 42, "func": "handler"}` or a plain string.
+fn format_src(src: &serde_json::Value) -> Option<String> {
+    match src {
+        serde_json::Value::String(src) => Some(src.to_owned()),
+        serde_json::Value::Object(src) => {
+            let file = src.get("file")?.as_str()?;
+            let location = match src.get("line").and_then(serde_json::Value::as_u64) {
+                Some(line) => format!("{}:{}", file, line),
+                None => file.to_owned(),
+            };
+            match src.get("func").and_then(serde_json::Value::as_str) {
+                Some(func) => Some(format!("{} in {}", location, func)),
+                None => Some(location),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// An `err` field gets special treatment if it looks like a serialized error.
+fn is_error(err: &serde_json::Map<String, serde_json::Value>) -> bool {
+    err.get("message").is_some() && matches!(err.get("stack"), Some(serde_json::Value::String(_)))
+}
+
+/// A `req` field gets special treatment if it looks like a serialized HTTP request.
+fn is_request(req: &serde_json::Map<String, serde_json::Value>) -> bool {
+    req.get("method").is_some_and(serde_json::Value::is_string)
+        && req.get("url").is_some_and(serde_json::Value::is_string)
+}
+
+/// Render the body of an HTTP request/response, separated from the headers by a blank line.
+///
+/// With `FormatOptions::pretty_req_body`, JSON bodies logged as strings (e.g. raw bodies) or as
+/// arrays are pretty-printed like objects.
+fn format_body(body: Option<&serde_json::Value>, options: &FormatOptions) -> String {
+    let pretty = |body: &serde_json::Value| {
+        format!(
+            "\n\n{}",
+            json_value_to_indented_string(body, options.json_indent)
+        )
     };
-    format!("{}\n{}", formatted_extras, formatted_details)
+    match body {
+        Some(serde_json::Value::String(body)) if body.is_empty() => "".into(),
+        Some(serde_json::Value::String(body)) if options.pretty_req_body => {
+            match serde_json::from_str::<serde_json::Value>(body) {
+                Ok(json @ (serde_json::Value::Object(_) | serde_json::Value::Array(_))) => {
+                    pretty(&json)
+                }
+                _ => format!("\n\n{}", body),
+            }
+        }
+        Some(serde_json::Value::String(body)) => format!("\n\n{}", body),
+        Some(body @ serde_json::Value::Object(_)) => pretty(body),
+        Some(body @ serde_json::Value::Array(_)) if options.pretty_req_body => pretty(body),
+        _ => "".into(),
+    }
 }
 
-/// Serialize a JSON value to a string using the specified indentation.
+/// A `res` field gets special treatment if it looks like a serialized HTTP response.
+fn is_response(res: &serde_json::Map<String, serde_json::Value>) -> bool {
+    res.get("statusCode").is_some_and(serde_json::Value::is_u64) && response_headers(res).is_some()
+}
+
+/// Node's `ServerResponse` uses `header`, other serializers use `headers`: prefer the former.
+fn response_headers(
+    res: &serde_json::Map<String, serde_json::Value>,
+) -> Option<&serde_json::Value> {
+    ["header", "headers"]
+        .iter()
+        .filter_map(|key| res.get(*key))
+        .find(|headers| headers.is_string() || headers.is_object())
+}
+
+/// Reason phrase of the most common HTTP status codes, empty if unknown.
+fn status_reason(status_code: u64) -> &'static str {
+    match status_code {
+        100 => "Continue",
+        101 => "Switching Protocols",
+        200 => "OK",
+        201 => "Created",
+        202 => "Accepted",
+        204 => "No Content",
+        206 => "Partial Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        303 => "See Other",
+        304 => "Not Modified",
+        307 => "Temporary Redirect",
+        308 => "Permanent Redirect",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        406 => "Not Acceptable",
+        408 => "Request Timeout",
+        409 => "Conflict",
+        410 => "Gone",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        422 => "Unprocessable Entity",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        501 => "Not Implemented",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "",
+    }
+}
+
+/// Render HTTP headers, one `name: value` per line, each line preceded by a newline.
+/// Headers that have already been rendered to a raw string are passed through as they are.
+fn format_headers(headers: &serde_json::Value) -> String {
+    match headers {
+        serde_json::Value::String(raw) => format!("\n{}", raw.trim_end()),
+        serde_json::Value::Object(headers) => headers
+            .iter()
+            .map(|(name, value)| match value {
+                serde_json::Value::String(s) => format!("\n{}: {}", name, s),
+                value => format!("\n{}: {}", name, value),
+            })
+            .collect(),
+        _ => "".into(),
+    }
+}
+
+/// Serialize a value to a JSON string using the specified indentation.
 ///
 /// It mimics the implementation of `serde_json::to_string_pretty`.
-fn json_to_indented_string(value: &serde_json::Value, indent: &str) -> String {
+fn json_to_indented_string<T: Serialize>(value: &T, indent: &str) -> serde_json::Result<String> {
     let mut writer = Vec::with_capacity(128);
     let formatter = PrettyFormatter::with_indent(indent.as_bytes());
     let mut serializer = Serializer::with_formatter(&mut writer, formatter);
-    value.serialize(&mut serializer).unwrap();
+    value.serialize(&mut serializer)?;
     unsafe {
         // We do not emit invalid UTF-8.
-        String::from_utf8_unchecked(writer)
+        Ok(String::from_utf8_unchecked(writer))
+    }
+}
+
+/// Like `json_to_indented_string`, for a JSON value indented by `indent` spaces.
+///
+/// Serializing a `serde_json::Value` to memory cannot fail: its keys are all strings. The
+/// compact rendering is used as a fallback all the same.
+fn json_value_to_indented_string(value: &serde_json::Value, indent: usize) -> String {
+    json_to_indented_string(value, &" ".repeat(indent)).unwrap_or_else(|_| value.to_string())
+}
+
+/// Replace nested objects and arrays by their leaves, keyed by their dotted path, e.g.
+/// `{"a": {"b": [1]}}` becomes `{"a.b.0": 1}`, see `FormatOptions::flatten`.
+///
+/// Empty objects and arrays are leaves.
+fn flatten(
+    extras: &serde_json::Map<String, serde_json::Value>,
+) -> serde_json::Map<String, serde_json::Value> {
+    fn flatten_into(
+        flattened: &mut serde_json::Map<String, serde_json::Value>,
+        key: String,
+        value: &serde_json::Value,
+    ) {
+        match value {
+            serde_json::Value::Object(object) if !object.is_empty() => {
+                for (field, value) in object {
+                    flatten_into(flattened, format!("{}.{}", key, field), value);
+                }
+            }
+            serde_json::Value::Array(array) if !array.is_empty() => {
+                for (i, value) in array.iter().enumerate() {
+                    flatten_into(flattened, format!("{}.{}", key, i), value);
+                }
+            }
+            value => {
+                flattened.insert(key, value.clone());
+            }
+        }
     }
+
+    let mut flattened = serde_json::Map::new();
+    for (key, value) in extras {
+        flatten_into(&mut flattened, key.clone(), value);
+    }
+    flattened
+}
+
+/// Cut `s` down to `limit` bytes (rounded down to a character boundary), noting the original
+/// length, see `FormatOptions::truncate_values`.
+fn truncate(s: &str, limit: Option<usize>) -> Cow<'_, str> {
+    match limit {
+        Some(limit) if s.len() > limit => {
+            let end = (0..=limit).rev().find(|i| s.is_char_boundary(*i)).unwrap();
+            Cow::Owned(format!("{}… (truncated, {} bytes)", &s[..end], s.len()))
+        }
+        _ => Cow::Borrowed(s),
+    }
+}
+
+/// The first line of `s`, cut down to `limit` characters: an ellipsis stands for what was cut.
+fn cut(s: &str, limit: Option<usize>) -> Cow<'_, str> {
+    let line = s.lines().next().unwrap_or_default();
+    let limit = limit.unwrap_or(usize::MAX);
+    if line.len() == s.len() && line.chars().count() <= limit {
+        return Cow::Borrowed(s);
+    }
+    // Not even room for the ellipsis.
+    if limit == 0 {
+        return Cow::Borrowed("");
+    }
+    let kept = line.chars().count().min(limit.saturating_sub(1));
+    Cow::Owned(
+        line.chars()
+            .take(kept)
+            .chain(std::iter::once('…'))
+            .collect(),
+    )
+}
+
+/// An array of scalars on a single line, e.g. `[a,b,3]`, or `None` if some of its elements are
+/// arrays or objects.
+///
+/// Strings are quoted like JSON strings if they would be ambiguous otherwise.
+fn inline_array(array: &[serde_json::Value]) -> Option<String> {
+    let elements = array.iter().map(|element| match element {
+        serde_json::Value::String(s)
+            if needs_quotes(s) || s.contains(|c: char| ",[]\"".contains(c) || c.is_control()) =>
+        {
+            Some(serde_json::to_string(s).unwrap())
+        }
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Array(_) | serde_json::Value::Object(_) => None,
+        scalar => Some(scalar.to_string()),
+    });
+    Some(format!(
+        "[{}]",
+        elements.collect::<Option<Vec<_>>>()?.join(",")
+    ))
 }
 
-pub fn indent(s: &str) -> String {
-    format!("    {}", s.lines().join("\n    "))
+/// Strings are shown as they are, unless they contain whitespaces or are empty: in that case,
+/// we want surrounding quotes.
+fn needs_quotes(s: &str) -> bool {
+    s.contains(' ') || s.is_empty()
+}
+
+/// Append a logfmt value, quoted if needed, or as requested by `style`: values with control
+/// characters are always quoted, for each record to stay on its own line.
+///
+/// Quoted values are escaped like JSON strings, so quotes, backslashes and newlines are
+/// escaped.
+fn write_logfmt_value(buffer: &mut String, s: &str, style: QuoteStyle) {
+    let quoted = match style {
+        QuoteStyle::Minimal => needs_quotes(s) || s.contains(['"', '=', '\\']),
+        QuoteStyle::Always => true,
+        QuoteStyle::Never => false,
+    };
+    if quoted || s.contains(char::is_control) {
+        buffer.push_str(&serde_json::to_string(s).unwrap());
+    } else {
+        buffer.push_str(s);
+    }
+}
+
+/// Pad the prefix of a record, rendered in `buffer` from `start` on, so that its message lines
+/// up with the others (see `FormatOptions::align`).
+fn align(buffer: &mut String, start: usize, options: &FormatOptions, state: &mut FormatState) {
+    let width = visible_width(&buffer[start..]);
+    let column = match options.align {
+        None => return,
+        Some(Align::Column(column)) => column,
+        Some(Align::Widest) => state.widen_prefix(width),
+    };
+    buffer.extend(std::iter::repeat_n(' ', column.saturating_sub(width)));
+}
+
+/// The number of characters of `s`, ANSI escape sequences excluded.
+fn visible_width(s: &str) -> usize {
+    let mut width = 0;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            // Skip the sequence, up to its final letter (e.g. `m`).
+            chars.by_ref().find(char::is_ascii_alphabetic);
+        } else {
+            width += 1;
+        }
+    }
+    width
+}
+
+/// Prefix each line of `s` with `width` spaces.
+pub fn indent(s: &str, width: usize) -> String {
+    let prefix = " ".repeat(width);
+    format!("{}{}", prefix, s.lines().join(&format!("\n{}", prefix)))
+}
+
+mod numeric_or_named_level {
+    use crate::NamedLogLevel;
+    use serde::{Deserialize, Deserializer};
+
+    /// The level of records without one, unless specified otherwise (see
+    /// `FormatOptions::default_level`).
+    pub fn default() -> u8 {
+        NamedLogLevel::Info as u8
+    }
+
+    /// Accept numerical levels (bunyan) as well as the names of the canonical levels, e.g.
+    /// `"info"`, used by some other loggers.
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<u8, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(serde::Deserialize)]
+        #[serde(untagged)]
+        pub enum NumericOrNamedLevel {
+            Numeric(u8),
+            Named(String),
+        }
+
+        match NumericOrNamedLevel::deserialize(deserializer)? {
+            NumericOrNamedLevel::Numeric(level) => Ok(level),
+            NumericOrNamedLevel::Named(name) => name
+                .parse::<NamedLogLevel>()
+                .map(|level| level as u8)
+                .map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+mod string_or_json {
+    use serde::{Deserialize, Deserializer};
+    use std::borrow::Cow;
+
+    /// Accept any JSON value: strings as they are, other values as compact JSON (e.g. `42` or
+    /// `{"a":1}`), so that records logged with a message that is not a string are not lost.
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Cow<'static, str>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(match serde_json::Value::deserialize(deserializer)? {
+            serde_json::Value::String(s) => Cow::Owned(s),
+            value => Cow::Owned(value.to_string()),
+        })
+    }
 }
 
 mod iso8601_or_timestamp {
-    use chrono::{DateTime, TimeZone, Utc};
-    use serde::{self, Deserialize, Deserializer};
+    use chrono::{DateTime, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+    use serde::{self, Deserialize, Deserializer, Serializer};
+
+    /// Always serialize back to ISO 8601, regardless of the representation we parsed.
+    pub fn serialize<S>(time: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&time.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+    }
 
+    /// Numeric timestamps below this value are interpreted as seconds since the epoch,
+    /// milliseconds otherwise: 1e12 milliseconds are September 2001, while 1e12 seconds are
+    /// more than 30 thousand years away.
+    const SECONDS_THRESHOLD: i64 = 1_000_000_000_000;
+
+    /// The formats of the timestamps without a timezone that are accepted.
+    const NAIVE_FORMATS: [&str; 2] = ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"];
+
+    /// Parse a timestamp without a timezone, e.g. `2012-02-08T22:56:52.856`.
+    pub fn parse_naive(s: &str) -> Option<NaiveDateTime> {
+        NAIVE_FORMATS
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())
+    }
+
+    /// Accept ISO 8601 strings (bunyan) as well as timestamps since the epoch (e.g. pino),
+    /// either in milliseconds or in (possibly fractional) seconds.
+    ///
+    /// ISO 8601 strings without a timezone are assumed to be in UTC, see
+    /// `FormatOptions::assume_tz`.
     pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
     where
         D: Deserializer<'de>,
@@ -147,19 +1586,40 @@ mod iso8601_or_timestamp {
         #[serde(untagged)]
         pub enum DateTimeOrTimestamp {
             DateTime(DateTime<Utc>),
+            // Integers must be tried before floats, or they would be coerced to floats.
             Timestamp(i64),
+            FractionalTimestamp(f64),
+            Naive(String),
         }
 
         let value = DateTimeOrTimestamp::deserialize(deserializer)?;
 
-        match value {
-            DateTimeOrTimestamp::DateTime(s) => DateTime::parse_from_rfc3339(&s.to_rfc3339())
-                .map_err(serde::de::Error::custom)
-                .map(|dt| dt.with_timezone(&Utc)),
-            DateTimeOrTimestamp::Timestamp(i) => match Utc.timestamp_millis_opt(i) {
-                chrono::LocalResult::Single(ts) => Ok(ts),
-                _ => Err(serde::de::Error::custom("invalid date format")),
+        let timestamp = match value {
+            DateTimeOrTimestamp::DateTime(s) => {
+                return DateTime::parse_from_rfc3339(&s.to_rfc3339())
+                    .map_err(serde::de::Error::custom)
+                    .map(|dt| dt.with_timezone(&Utc))
+            }
+            DateTimeOrTimestamp::Timestamp(i) if i.unsigned_abs() < SECONDS_THRESHOLD as u64 => {
+                Utc.timestamp_opt(i, 0)
+            }
+            DateTimeOrTimestamp::Timestamp(i) => Utc.timestamp_millis_opt(i),
+            // f64 cannot represent nanoseconds at the magnitude of current timestamps:
+            // microseconds are as precise as we can reliably get.
+            DateTimeOrTimestamp::FractionalTimestamp(f) if f.abs() < SECONDS_THRESHOLD as f64 => {
+                Utc.timestamp_micros((f * 1e6).round() as i64)
+            }
+            DateTimeOrTimestamp::FractionalTimestamp(f) => {
+                Utc.timestamp_micros((f * 1e3).round() as i64)
+            }
+            DateTimeOrTimestamp::Naive(s) => match parse_naive(&s) {
+                Some(naive) => Utc.from_local_datetime(&naive),
+                None => chrono::LocalResult::None,
             },
+        };
+        match timestamp {
+            chrono::LocalResult::Single(ts) => Ok(ts),
+            _ => Err(serde::de::Error::custom("invalid date format")),
         }
     }
 }
@@ -0,0 +1,89 @@
+use flate2::bufread::MultiGzDecoder;
+use std::io::{BufRead, BufReader};
+
+const GZIP_MAGIC_BYTES: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC_BYTES: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+const BZIP2_MAGIC_BYTES: [u8; 3] = *b"BZh";
+// What follows the block size: the start of the first block, or the end of an empty stream.
+const BZIP2_BLOCK_MAGIC_BYTES: [u8; 6] = *b"1AY&SY";
+const BZIP2_END_MAGIC_BYTES: [u8; 6] = [0x17, 0x72, 0x45, 0x38, 0x50, 0x90];
+
+/// Compression formats we can transparently decompress.
+///
+/// zstd and bzip2 are only supported if the `zstd` and `bzip2` features are enabled (they are
+/// by default): otherwise such inputs are still recognized, to report an error.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Compression {
+    Gzip,
+    Zstd,
+    Bzip2,
+}
+
+impl Compression {
+    /// Guess the compression format from a file extension, e.g. `app.log.gz`.
+    pub(crate) fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "gz" => Some(Compression::Gzip),
+            "zst" => Some(Compression::Zstd),
+            "bz2" => Some(Compression::Bzip2),
+            _ => None,
+        }
+    }
+
+    /// Detect the compression format from the first bytes of the input, without consuming them.
+    pub(crate) fn detect<R: BufRead>(input: &mut R) -> std::io::Result<Option<Self>> {
+        let header = input.fill_buf()?;
+        if header.starts_with(&GZIP_MAGIC_BYTES) {
+            Ok(Some(Compression::Gzip))
+        } else if header.starts_with(&ZSTD_MAGIC_BYTES) {
+            Ok(Some(Compression::Zstd))
+        } else if is_bzip2_header(header) {
+            Ok(Some(Compression::Bzip2))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Whether `header` starts with a whole bzip2 header, so that text lines starting with `BZh` are
+/// not taken for one.
+fn is_bzip2_header(header: &[u8]) -> bool {
+    match header.strip_prefix(&BZIP2_MAGIC_BYTES) {
+        Some([b'1'..=b'9', rest @ ..]) => {
+            rest.starts_with(&BZIP2_BLOCK_MAGIC_BYTES) || rest.starts_with(&BZIP2_END_MAGIC_BYTES)
+        }
+        _ => false,
+    }
+}
+
+/// Wrap `input` in the appropriate decoder, if it is compressed.
+///
+/// If `compression` is not known in advance it is detected using magic bytes.
+pub(crate) fn decompress<'a, R: BufRead + 'a>(
+    mut input: R,
+    compression: Option<Compression>,
+) -> std::io::Result<Box<dyn BufRead + 'a>> {
+    let compression = match compression {
+        Some(compression) => Some(compression),
+        None => Compression::detect(&mut input)?,
+    };
+    Ok(match compression {
+        Some(Compression::Gzip) => Box::new(BufReader::new(MultiGzDecoder::new(input))),
+        #[cfg(feature = "zstd")]
+        Some(Compression::Zstd) => Box::new(BufReader::new(
+            zstd::stream::read::Decoder::with_buffer(input)?,
+        )),
+        #[cfg(feature = "bzip2")]
+        Some(Compression::Bzip2) => {
+            Box::new(BufReader::new(bzip2::bufread::MultiBzDecoder::new(input)))
+        }
+        #[allow(unreachable_patterns)]
+        Some(compression) => {
+            return Err(std::io::Error::other(format!(
+                "{:?} inputs are not supported by this build of bunyan",
+                compression
+            )))
+        }
+        None => Box::new(input),
+    })
+}
@@ -0,0 +1,81 @@
+use super::compression::{decompress, Compression};
+use super::interrupt::Interruptible;
+use super::output::GroupedOutput;
+use super::stdin::{process_input_with_context, process_input_with_state};
+use crate::{FormatOptions, FormatState, Stats};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+/// Process each file in order, as if they had been concatenated, except for relative times
+/// (see `TimeMode::Relative`): they start over with each file. `-` stands for stdin.
+/// Compressed files are decompressed on the fly.
+///
+/// Files that cannot be opened are reported on stderr and skipped: it returns `false` if that
+/// happened for at least one of them. Files after the `FormatOptions::max_count`-th matching
+/// record are not opened at all.
+pub fn process_files<W: Write>(
+    paths: &[PathBuf],
+    output: &mut W,
+    options: &FormatOptions,
+    stats: &mut Stats,
+) -> std::io::Result<bool> {
+    match &options.group_by {
+        // Records are grouped across all the files.
+        Some(field) => {
+            let mut grouped = GroupedOutput::new(output, field);
+            let all_opened = process_each_file(paths, options, stats, |input, state, stats| {
+                process_input_with_context(input, &mut grouped, options, state, stats)
+            })?;
+            grouped.finish(options)?;
+            Ok(all_opened)
+        }
+        None => process_each_file(paths, options, stats, |input, state, stats| {
+            process_input_with_state(input, output, options, state, stats)
+        }),
+    }
+}
+
+/// Open each file in turn, for `process` to read it, see `process_files`.
+fn process_each_file<F>(
+    paths: &[PathBuf],
+    options: &FormatOptions,
+    stats: &mut Stats,
+    mut process: F,
+) -> std::io::Result<bool>
+where
+    F: FnMut(Box<dyn BufRead + '_>, &mut FormatState, &mut Stats) -> std::io::Result<()>,
+{
+    let mut all_opened = true;
+    let mut state = FormatState::new();
+    for path in paths {
+        if options.is_done(stats) {
+            break;
+        }
+        state.reset_time_baseline();
+        if path.as_os_str() == "-" {
+            let stdin = std::io::stdin();
+            process(
+                decompress(Interruptible(stdin.lock()), None)?,
+                &mut state,
+                stats,
+            )?;
+            continue;
+        }
+        let compression = path
+            .extension()
+            .and_then(|extension| Compression::from_extension(&extension.to_string_lossy()));
+        match File::open(path) {
+            Ok(file) => process(
+                decompress(BufReader::new(file), compression)?,
+                &mut state,
+                stats,
+            )?,
+            Err(e) => {
+                eprintln!("bunyan: {}: {}", path.display(), e);
+                all_opened = false;
+            }
+        }
+    }
+    Ok(all_opened)
+}
@@ -0,0 +1,138 @@
+use super::output::ContextOutput;
+use super::process_line;
+use super::stdin::skip_bom;
+use crate::{interrupted, FormatOptions, FormatState, Stats};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// How long to wait before polling the followed files again when none of them had new data.
+pub(super) const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A file being followed, `tail -f` style.
+struct FollowedFile<'a> {
+    path: &'a Path,
+    reader: BufReader<File>,
+    /// How far we have read into the file.
+    position: u64,
+    /// The number of complete lines read so far.
+    lines: usize,
+    /// A line that has been partially written: we hold on to it until its newline shows up.
+    ///
+    /// It is kept as bytes, as the last character written so far might be incomplete.
+    pending: Vec<u8>,
+}
+
+impl<'a> FollowedFile<'a> {
+    fn open(path: &'a Path) -> std::io::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let position = skip_bom(&mut reader)? as u64;
+        Ok(FollowedFile {
+            path,
+            reader,
+            position,
+            lines: 0,
+            pending: Vec::new(),
+        })
+    }
+
+    /// Read the next complete line, if there is one.
+    fn next_line(&mut self) -> std::io::Result<Option<String>> {
+        let read = self.reader.read_until(b'\n', &mut self.pending)?;
+        if read == 0 {
+            self.reopen_if_truncated()?;
+            return Ok(None);
+        }
+        self.position += read as u64;
+        if self.pending.ends_with(b"\n") {
+            let mut line = std::mem::take(&mut self.pending);
+            line.pop();
+            self.lines += 1;
+            Ok(Some(String::from_utf8_lossy(&line).into_owned()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// If the file has shrunk it has been truncated or rotated: start again from the top.
+    fn reopen_if_truncated(&mut self) -> std::io::Result<()> {
+        match std::fs::metadata(self.path) {
+            Ok(metadata) if metadata.len() < self.position => {
+                *self = FollowedFile::open(self.path)?;
+                Ok(())
+            }
+            // The file might be missing for a moment while it is being rotated.
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Process each file and, once their end is reached, keep polling them for new lines.
+///
+/// All files are followed concurrently: new lines are processed as they show up, regardless of
+/// the file they come from.
+/// It only returns once `FormatOptions::max_count` records passed the filters, on Ctrl-C (see
+/// `catch_interrupts`), if none of the files could be opened or on I/O errors.
+/// The files that could be opened are followed regardless of the other ones: it returns
+/// `false` if any failed to open.
+pub fn follow_files<W: Write>(
+    paths: &[PathBuf],
+    output: &mut W,
+    options: &FormatOptions,
+    stats: &mut Stats,
+) -> std::io::Result<bool> {
+    let mut files = Vec::with_capacity(paths.len());
+    let mut all_opened = true;
+    for path in paths {
+        if path.as_os_str() == "-" {
+            eprintln!("bunyan: cannot follow stdin");
+            all_opened = false;
+            continue;
+        }
+        match FollowedFile::open(path) {
+            Ok(file) => files.push(file),
+            Err(e) => {
+                eprintln!("bunyan: {}: {}", path.display(), e);
+                all_opened = false;
+            }
+        }
+    }
+    if files.is_empty() {
+        return Ok(false);
+    }
+
+    let mut output = ContextOutput::new(output, options.before_context, options.after_context);
+    let mut formatted = String::new();
+    let mut state = FormatState::new();
+    loop {
+        let mut idle = true;
+        for file in &mut files {
+            while let Some(line) = file.next_line()? {
+                idle = false;
+                // Followed files keep growing: there is no end of the input to skip to.
+                process_line(
+                    &line,
+                    file.lines,
+                    &mut formatted,
+                    &mut output,
+                    options,
+                    &mut state,
+                    stats,
+                )?;
+                if options.is_done(stats) {
+                    output.finish()?;
+                    return Ok(all_opened);
+                }
+            }
+        }
+        if idle {
+            // Nothing new: Ctrl-C has to be checked for while waiting.
+            if interrupted() {
+                output.finish()?;
+                return Ok(all_opened);
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
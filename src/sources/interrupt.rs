@@ -0,0 +1,73 @@
+use std::io::{BufRead, Read};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::sync::OnceLock;
+
+/// Whether the user asked us to stop, see `catch_interrupts`.
+static INTERRUPTED: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+
+/// On Ctrl-C (i.e. `SIGINT`), stop reading inputs instead of dying on the spot: the lines read
+/// so far are then written out as usual, so that the output is neither cut mid-record nor left
+/// with a dangling color escape, and the statistics can still be shown.
+///
+/// Inputs are stopped between lines, or while waiting for one (e.g. from a quiet stdin or
+/// client). A second Ctrl-C exits right away, like the default handler would.
+/// It does nothing on platforms without signals.
+pub fn catch_interrupts() {
+    #[cfg(unix)]
+    {
+        use signal_hook::consts::SIGINT;
+        use signal_hook::flag;
+
+        let interrupted = INTERRUPTED.get_or_init(Default::default);
+        // Registered first, so that it only exits once the flag is already set.
+        let registered = flag::register_conditional_shutdown(SIGINT, 130, Arc::clone(interrupted))
+            .and_then(|_| flag::register(SIGINT, Arc::clone(interrupted)));
+        if let Err(e) = registered {
+            eprintln!("bunyan: cannot catch Ctrl-C: {}", e);
+            return;
+        }
+        // Blocked reads are not restarted, for them to return and the inputs to be stopped.
+        // SAFETY: the handler registered above is kept as it is, only without `SA_RESTART`.
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            if libc::sigaction(SIGINT, std::ptr::null(), &mut action) == 0 {
+                action.sa_flags &= !libc::SA_RESTART;
+                libc::sigaction(SIGINT, &action, std::ptr::null_mut());
+            }
+        }
+    }
+}
+
+/// Whether Ctrl-C was hit since `catch_interrupts` was called.
+pub fn interrupted() -> bool {
+    INTERRUPTED
+        .get()
+        .is_some_and(|interrupted| interrupted.load(Ordering::SeqCst))
+}
+
+/// An input that ends when Ctrl-C interrupts a read from it, rather than retrying as readers
+/// usually do: see `catch_interrupts`.
+pub(crate) struct Interruptible<R>(pub(crate) R);
+
+impl<R: Read> Read for Interruptible<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self.0.read(buf) {
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted && interrupted() => Ok(0),
+            result => result,
+        }
+    }
+}
+
+impl<R: BufRead> BufRead for Interruptible<R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        match self.0.fill_buf() {
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted && interrupted() => Ok(&[]),
+            result => result,
+        }
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.0.consume(amount)
+    }
+}
@@ -0,0 +1,84 @@
+use super::output::Output;
+use super::process_line;
+use crate::{FormatOptions, FormatState, Stats};
+use serde_json::value::RawValue;
+use std::io::BufRead;
+
+/// Detect whether the input may be a single JSON array of records (e.g. `[{...},{...}]`) rather
+/// than one record per line, without consuming it: see `parse_array` for whether it is one.
+///
+/// Only the first buffered bytes are inspected: the array must open with `[` followed by `{`
+/// or `]`, so that text lines starting with a bracket (e.g. `[2012-02-08T22:56:52.856Z] ...`)
+/// are still handled line by line.
+pub(crate) fn is_json_array<R: BufRead>(input: &mut R) -> std::io::Result<bool> {
+    let mut tokens = input
+        .fill_buf()?
+        .iter()
+        .filter(|byte| !byte.is_ascii_whitespace());
+    Ok(tokens.next() == Some(&b'[') && matches!(tokens.next(), Some(b'{') | Some(b']')))
+}
+
+/// The elements of `document` if it is a JSON array as a whole, up to its end.
+///
+/// Inputs that only start like one (e.g. `[] starting` or `[{...}]` followed by other lines)
+/// are not arrays, to be processed line by line instead.
+pub(crate) fn parse_array(document: &[u8]) -> Option<Vec<&RawValue>> {
+    serde_json::from_slice(document).ok()
+}
+
+/// Format each element of a JSON array as if it were a line of input, numbered by its position
+/// in the array.
+///
+/// Elements are formatted from their text as it is in the input, e.g. to tell duplicate keys.
+pub(crate) fn process_array<O: Output>(
+    elements: Vec<&RawValue>,
+    output: &mut O,
+    options: &FormatOptions,
+    state: &mut FormatState,
+    stats: &mut Stats,
+) -> std::io::Result<()> {
+    let mut formatted = String::new();
+    for (i, element) in elements.iter().enumerate() {
+        if process_line(
+            element.get(),
+            i + 1,
+            &mut formatted,
+            output,
+            options,
+            state,
+            stats,
+        )? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Format each of the whitespace-separated JSON values of the input, e.g. pretty-printed records
+/// spanning several lines, as if it were a line of input, numbered by its position in the input.
+///
+/// Text that is not JSON cannot be told apart from a malformed record: it is an error.
+pub(crate) fn process_values<R: BufRead, O: Output>(
+    input: R,
+    output: &mut O,
+    options: &FormatOptions,
+    state: &mut FormatState,
+    stats: &mut Stats,
+) -> std::io::Result<()> {
+    let mut formatted = String::new();
+    let values = serde_json::Deserializer::from_reader(input).into_iter::<Box<RawValue>>();
+    for (i, value) in values.enumerate() {
+        if process_line(
+            value?.get(),
+            i + 1,
+            &mut formatted,
+            output,
+            options,
+            state,
+            stats,
+        )? {
+            break;
+        }
+    }
+    Ok(())
+}
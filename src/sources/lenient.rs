@@ -0,0 +1,85 @@
+use super::stdin::next_line;
+use std::collections::VecDeque;
+use std::io::BufRead;
+
+/// How many lines are read ahead, at most, to complete a record split by raw newlines.
+const MAX_JOINED_LINES: usize = 10;
+
+/// Where a scan of a JSON object stands, as far as braces and strings go.
+#[derive(Default)]
+struct Scan {
+    /// How many braces are open, outside of strings.
+    depth: usize,
+    in_string: bool,
+    /// Whether the previous character was a backslash, in a string.
+    escaped: bool,
+}
+
+impl Scan {
+    fn feed(&mut self, s: &str) {
+        for c in s.chars() {
+            match (self.in_string, self.escaped, c) {
+                (true, true, _) => self.escaped = false,
+                (true, false, '\\') => self.escaped = true,
+                (true, false, '"') | (false, _, '"') => self.in_string = !self.in_string,
+                (false, _, '{') => self.depth += 1,
+                (false, _, '}') => self.depth = self.depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.depth == 0 && !self.in_string
+    }
+}
+
+/// If `line` is the start of a JSON object split by raw newlines (invalid JSON, but written by
+/// some loggers when a string contains a newline), join it with the following lines of
+/// `input`: it returns the whole object, newlines in strings escaped, along with the number of
+/// lines joined to `line`.
+///
+/// Lines that do not make a JSON object, up to `MAX_JOINED_LINES`, are put back in `pending`
+/// to be processed on their own, and `line` is returned as is.
+pub(super) fn join_split_lines<R: BufRead>(
+    line: String,
+    input: &mut R,
+    buffer: &mut Vec<u8>,
+    pending: &mut VecDeque<String>,
+) -> std::io::Result<(String, usize)> {
+    if !line.trim_start().starts_with('{') {
+        return Ok((line, 0));
+    }
+    let mut scan = Scan::default();
+    scan.feed(&line);
+    if scan.is_complete() {
+        return Ok((line, 0));
+    }
+    let mut joined = line.clone();
+    let mut read = Vec::new();
+    while read.len() < MAX_JOINED_LINES {
+        let next = match pending.pop_front() {
+            Some(next) => next,
+            None => match next_line(input, buffer)? {
+                Some(next) => next,
+                None => break,
+            },
+        };
+        // A newline outside of strings is whitespace, as far as JSON is concerned.
+        joined.push_str(if scan.in_string { "\\n" } else { "\n" });
+        let next = next.strip_suffix('\r').unwrap_or(&next).to_owned();
+        scan.feed(&next);
+        joined.push_str(&next);
+        read.push(next);
+        if scan.is_complete() {
+            if serde_json::from_str::<serde::de::IgnoredAny>(&joined).is_ok() {
+                return Ok((joined, read.len()));
+            }
+            break;
+        }
+    }
+    for next in read.into_iter().rev() {
+        pending.push_front(next);
+    }
+    Ok((line, 0))
+}
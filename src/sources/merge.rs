@@ -0,0 +1,168 @@
+use super::compression::{decompress, Compression};
+use super::interrupt::Interruptible;
+use super::output::{ContextOutput, GroupedOutput, Output};
+use super::stdin::{next_line, parse_line, process_parsed_line, skip_bom};
+use crate::record::ParsedLine;
+use crate::{FormatOptions, FormatState, LogRecord, Stats};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+/// An input being merged with the others.
+struct MergedInput {
+    reader: Box<dyn BufRead>,
+    buffer: Vec<u8>,
+    /// The number of lines read so far.
+    lines: usize,
+}
+
+/// The next record of an input, held back until it is the earliest of all inputs.
+struct Head {
+    line_number: usize,
+    line: String,
+    record: LogRecord<'static>,
+}
+
+impl MergedInput {
+    /// Read up to the next record of the input.
+    ///
+    /// The lines that are not records on the way are processed as they are read: they stick
+    /// to the record they follow.
+    fn next_record<O: Output>(
+        &mut self,
+        formatted: &mut String,
+        output: &mut O,
+        options: &FormatOptions,
+        state: &mut FormatState,
+        stats: &mut Stats,
+    ) -> std::io::Result<Option<Head>> {
+        while let Some(line) = next_line(&mut self.reader, &mut self.buffer)? {
+            self.lines += 1;
+            let (content, parsed) = parse_line(&line, self.lines, options);
+            if let ParsedLine::Record(record) = parsed {
+                // Kept as parsed, for it not to be parsed again once it is processed.
+                let record = record.into_owned();
+                return Ok(Some(Head {
+                    line_number: self.lines,
+                    line,
+                    record,
+                }));
+            }
+            let ends_input = process_parsed_line(
+                content, &parsed, self.lines, formatted, output, options, state, stats,
+            )?;
+            if ends_input {
+                break;
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Process all the files at once, interleaving their records by time. `-` stands for stdin.
+/// Compressed files are decompressed on the fly.
+///
+/// Each file is expected to be sorted by time already: only the next record of each of them is
+/// held back, and records logged at the same time are taken from the files in order.
+/// Files are read line by line: JSON arrays and multi-line values are not supported, nor is raw
+/// mode.
+///
+/// Files that cannot be opened are reported on stderr and skipped: it returns `false` if that
+/// happened for at least one of them.
+pub fn merge_files<W: Write>(
+    paths: &[PathBuf],
+    output: &mut W,
+    options: &FormatOptions,
+    stats: &mut Stats,
+) -> std::io::Result<bool> {
+    let mut inputs = Vec::with_capacity(paths.len());
+    let mut all_opened = true;
+    for path in paths {
+        let mut reader = if path.as_os_str() == "-" {
+            decompress(Interruptible(std::io::stdin().lock()), None)?
+        } else {
+            let compression = path
+                .extension()
+                .and_then(|extension| Compression::from_extension(&extension.to_string_lossy()));
+            match File::open(path) {
+                Ok(file) => decompress(BufReader::new(file), compression)?,
+                Err(e) => {
+                    eprintln!("bunyan: {}: {}", path.display(), e);
+                    all_opened = false;
+                    continue;
+                }
+            }
+        };
+        skip_bom(&mut reader)?;
+        inputs.push(MergedInput {
+            reader,
+            buffer: Vec::new(),
+            lines: 0,
+        });
+    }
+
+    // Relative times are relative to the first record of all the files.
+    let mut state = FormatState::new();
+    match &options.group_by {
+        Some(field) => {
+            let mut grouped = GroupedOutput::new(output, field);
+            merge_inputs(&mut inputs, &mut grouped, options, &mut state, stats)?;
+            grouped.finish(options)?;
+        }
+        None => {
+            let mut output =
+                ContextOutput::new(output, options.before_context, options.after_context);
+            merge_inputs(&mut inputs, &mut output, options, &mut state, stats)?;
+            output.finish()?;
+        }
+    }
+    Ok(all_opened)
+}
+
+/// Process the records of all the inputs, earliest first, see `merge_files`.
+fn merge_inputs<O: Output>(
+    inputs: &mut [MergedInput],
+    output: &mut O,
+    options: &FormatOptions,
+    state: &mut FormatState,
+    stats: &mut Stats,
+) -> std::io::Result<()> {
+    let mut formatted = String::new();
+    // The next record of each input, held back along with the time and the order of the inputs.
+    let mut heads = Vec::with_capacity(inputs.len());
+    let mut order = BinaryHeap::with_capacity(inputs.len());
+    for (i, input) in inputs.iter_mut().enumerate() {
+        let head = input.next_record(&mut formatted, output, options, state, stats)?;
+        if let Some(head) = &head {
+            order.push(Reverse((head.record.time, i)));
+        }
+        heads.push(head);
+    }
+    while let Some(Reverse((_, i))) = order.pop() {
+        if options.is_done(stats) {
+            break;
+        }
+        let head = heads[i].take().expect("inputs in the order have a head");
+        let parsed = ParsedLine::Record(head.record);
+        let ends_input = process_parsed_line(
+            &head.line,
+            &parsed,
+            head.line_number,
+            &mut formatted,
+            output,
+            options,
+            state,
+            stats,
+        )?;
+        if ends_input {
+            continue;
+        }
+        heads[i] = inputs[i].next_record(&mut formatted, output, options, state, stats)?;
+        if let Some(head) = &heads[i] {
+            order.push(Reverse((head.record.time, i)));
+        }
+    }
+    Ok(())
+}
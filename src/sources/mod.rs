@@ -1,3 +1,19 @@
+mod compression;
+mod file;
+mod follow;
+mod interrupt;
+mod json;
+mod lenient;
+mod merge;
+mod output;
+mod stats;
 mod stdin;
+mod tcp;
 
+pub use file::*;
+pub use follow::*;
+pub use interrupt::*;
+pub use merge::*;
+pub use stats::*;
 pub use stdin::*;
+pub use tcp::*;
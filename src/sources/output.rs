@@ -0,0 +1,320 @@
+use crate::record::paint_into;
+use crate::{FormatOptions, LogRecord};
+use chrono::{DateTime, Utc};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::io::Write;
+
+/// Where formatted lines end up.
+pub(crate) trait Output {
+    /// Write a formatted line, along with the time of the record it comes from, if any.
+    fn write_formatted(
+        &mut self,
+        formatted: &str,
+        time: Option<DateTime<Utc>>,
+    ) -> std::io::Result<()>;
+
+    /// Write a formatted record that passed the filters. It is written like any line with the
+    /// time of the record by default.
+    fn write_formatted_record(
+        &mut self,
+        formatted: &str,
+        record: &LogRecord,
+    ) -> std::io::Result<()> {
+        self.write_formatted(formatted, Some(record.time))
+    }
+
+    /// Write a formatted record that did not pass the filters, to be shown as the context of
+    /// the ones that did (see `FormatOptions::before_context`). It is dropped by default.
+    fn write_context(&mut self, _formatted: &str, _time: DateTime<Utc>) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn flush_formatted(&mut self) -> std::io::Result<()>;
+}
+
+impl<W: Write> Output for W {
+    fn write_formatted(
+        &mut self,
+        formatted: &str,
+        _time: Option<DateTime<Utc>>,
+    ) -> std::io::Result<()> {
+        self.write_all(formatted.as_bytes())
+    }
+
+    fn flush_formatted(&mut self) -> std::io::Result<()> {
+        self.flush()
+    }
+}
+
+/// A bounded reorder buffer: it holds on to up to `window` lines, writing the earliest one when
+/// it is full, so that records that are slightly out of order are written sorted by time.
+///
+/// Lines that do not come from records stick to the record they follow.
+pub(crate) struct SortedOutput<'a, W> {
+    output: &'a mut W,
+    window: usize,
+    /// Lines, by time and then by order of arrival.
+    buffer: BinaryHeap<Reverse<(DateTime<Utc>, u64, String)>>,
+    arrivals: u64,
+    /// The time of the last record.
+    last_time: DateTime<Utc>,
+}
+
+impl<'a, W: Write> SortedOutput<'a, W> {
+    pub(crate) fn new(output: &'a mut W, window: usize) -> Self {
+        SortedOutput {
+            output,
+            window,
+            buffer: BinaryHeap::with_capacity(window + 1),
+            arrivals: 0,
+            last_time: DateTime::<Utc>::MIN_UTC,
+        }
+    }
+
+    /// Write all the buffered lines, in order.
+    pub(crate) fn finish(mut self) -> std::io::Result<()> {
+        while let Some(Reverse((_, _, formatted))) = self.buffer.pop() {
+            self.output.write_all(formatted.as_bytes())?;
+        }
+        self.output.flush()
+    }
+}
+
+impl<W: Write> Output for SortedOutput<'_, W> {
+    fn write_formatted(
+        &mut self,
+        formatted: &str,
+        time: Option<DateTime<Utc>>,
+    ) -> std::io::Result<()> {
+        if let Some(time) = time {
+            self.last_time = time;
+        }
+        self.buffer.push(Reverse((
+            self.last_time,
+            self.arrivals,
+            formatted.to_owned(),
+        )));
+        self.arrivals += 1;
+        if self.buffer.len() > self.window {
+            if let Some(Reverse((_, _, earliest))) = self.buffer.pop() {
+                self.output.write_all(earliest.as_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn flush_formatted(&mut self) -> std::io::Result<()> {
+        self.output.flush()
+    }
+}
+
+/// Shows the records around the ones that passed the filters, like `grep -B` and `grep -A`:
+/// records that did not pass the filters are given to `write_context`.
+///
+/// Groups of records that are not contiguous are separated by `--`.
+/// Lines that are not records are written in order, as usual: they are held back along with
+/// the records before a possible match.
+pub(crate) struct ContextOutput<'a, O> {
+    output: &'a mut O,
+    before: usize,
+    after: usize,
+    /// The last records that did not pass the filters (`before` at most), in case the next
+    /// one does, along with the lines that are not records in between.
+    held: VecDeque<(String, Option<DateTime<Utc>>)>,
+    held_records: usize,
+    /// How many of the next records are shown as context of the last matching one.
+    remaining_after: usize,
+    /// Whether a record has been skipped since the last one that was written.
+    skipped: bool,
+    /// Whether a record has been written yet.
+    written: bool,
+}
+
+impl<'a, O: Output> ContextOutput<'a, O> {
+    pub(crate) fn new(output: &'a mut O, before: usize, after: usize) -> Self {
+        ContextOutput {
+            output,
+            before,
+            after,
+            held: VecDeque::with_capacity(before + 1),
+            held_records: 0,
+            remaining_after: 0,
+            skipped: false,
+            written: false,
+        }
+    }
+
+    /// Write the lines that are not records that are still held back.
+    pub(crate) fn finish(mut self) -> std::io::Result<()> {
+        while let Some((formatted, time)) = self.held.pop_front() {
+            if time.is_none() {
+                self.output.write_formatted(&formatted, None)?;
+            }
+        }
+        self.output.flush_formatted()
+    }
+
+    fn write_record(&mut self, formatted: &str, time: DateTime<Utc>) -> std::io::Result<()> {
+        if self.skipped && self.written {
+            self.output.write_formatted("--\n", None)?;
+        }
+        self.skipped = false;
+        self.written = true;
+        self.output.write_formatted(formatted, Some(time))
+    }
+
+    fn write_held(&mut self) -> std::io::Result<()> {
+        while let Some((formatted, time)) = self.held.pop_front() {
+            match time {
+                Some(time) => self.write_record(&formatted, time)?,
+                None => self.output.write_formatted(&formatted, None)?,
+            }
+        }
+        self.held_records = 0;
+        Ok(())
+    }
+}
+
+impl<O: Output> Output for ContextOutput<'_, O> {
+    fn write_formatted(
+        &mut self,
+        formatted: &str,
+        time: Option<DateTime<Utc>>,
+    ) -> std::io::Result<()> {
+        match time {
+            Some(time) => {
+                self.write_held()?;
+                self.remaining_after = self.after;
+                self.write_record(formatted, time)
+            }
+            None if self.held.is_empty() => self.output.write_formatted(formatted, None),
+            None => {
+                self.held.push_back((formatted.to_owned(), None));
+                Ok(())
+            }
+        }
+    }
+
+    fn write_context(&mut self, formatted: &str, time: DateTime<Utc>) -> std::io::Result<()> {
+        if self.remaining_after > 0 {
+            self.remaining_after -= 1;
+            return self.write_record(formatted, time);
+        }
+        self.held.push_back((formatted.to_owned(), Some(time)));
+        self.held_records += 1;
+        // Drop the oldest record, writing the lines that are not records before it.
+        while self.held_records > self.before {
+            match self.held.pop_front() {
+                Some((_, Some(_))) => {
+                    self.held_records -= 1;
+                    self.skipped = true;
+                }
+                Some((formatted, None)) => self.output.write_formatted(&formatted, None)?,
+                None => break,
+            }
+        }
+        Ok(())
+    }
+
+    fn flush_formatted(&mut self) -> std::io::Result<()> {
+        self.output.flush_formatted()
+    }
+}
+
+/// Holds all the records, grouped by the value of a field (see `FormatOptions::group_by`),
+/// for `finish` to write each group under a header, in the order its first record was written.
+/// Records without the field come last.
+///
+/// Lines that are not records stick to the record they follow.
+pub(crate) struct GroupedOutput<'a, O> {
+    output: &'a mut O,
+    /// The field, as given, e.g. `req.id`.
+    field: &'a str,
+    /// The field as a JSON pointer, e.g. `/req/id`.
+    pointer: String,
+    /// The lines before the first record.
+    leading: String,
+    /// The formatted records of each group, by value of the field: records without it are in
+    /// the `None` group.
+    groups: Vec<(Option<String>, String)>,
+    indices: HashMap<Option<String>, usize>,
+    /// The group of the last record.
+    current: Option<usize>,
+}
+
+impl<'a, O: Output> GroupedOutput<'a, O> {
+    /// Group records by `field`, nested fields being separated by dots (e.g. `req.id`).
+    pub(crate) fn new(output: &'a mut O, field: &'a str) -> Self {
+        GroupedOutput {
+            output,
+            field,
+            pointer: format!("/{}", field.replace('.', "/")),
+            leading: String::new(),
+            groups: Vec::new(),
+            indices: HashMap::new(),
+            current: None,
+        }
+    }
+
+    /// Write the groups, each under a header with the value of the field, records without the
+    /// field last, under `(ungrouped)`.
+    pub(crate) fn finish(mut self, options: &FormatOptions) -> std::io::Result<()> {
+        self.output.write_formatted(&self.leading, None)?;
+        // Stable: the other groups keep their order.
+        self.groups.sort_by_key(|(value, _)| value.is_none());
+        for (i, (value, formatted)) in self.groups.iter().enumerate() {
+            let mut header = String::new();
+            if i > 0 {
+                header.push('\n');
+            }
+            let title = match value {
+                Some(value) => format!("{}: {}", self.field, value),
+                None => "(ungrouped)".to_owned(),
+            };
+            paint_into(&mut header, &title, options.theme.group, options);
+            header.push('\n');
+            self.output.write_formatted(&header, None)?;
+            self.output.write_formatted(formatted, None)?;
+        }
+        self.output.flush_formatted()
+    }
+}
+
+impl<O: Output> Output for GroupedOutput<'_, O> {
+    fn write_formatted(
+        &mut self,
+        formatted: &str,
+        _time: Option<DateTime<Utc>>,
+    ) -> std::io::Result<()> {
+        match self.current {
+            Some(index) => self.groups[index].1.push_str(formatted),
+            None => self.leading.push_str(formatted),
+        }
+        Ok(())
+    }
+
+    fn write_formatted_record(
+        &mut self,
+        formatted: &str,
+        record: &LogRecord,
+    ) -> std::io::Result<()> {
+        let value = record.extract(&self.pointer).map(|value| match value {
+            serde_json::Value::String(s) => s,
+            value => value.to_string(),
+        });
+        let groups = &mut self.groups;
+        let index = *self.indices.entry(value.clone()).or_insert_with(|| {
+            groups.push((value, String::new()));
+            groups.len() - 1
+        });
+        self.groups[index].1.push_str(formatted);
+        self.current = Some(index);
+        Ok(())
+    }
+
+    /// Nothing is written before `finish`.
+    fn flush_formatted(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
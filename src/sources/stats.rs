@@ -0,0 +1,35 @@
+use crate::NAMED_LOG_LEVELS;
+use std::collections::BTreeMap;
+
+/// Counters updated while processing the input.
+///
+/// Its `Display` implementation is a summary, one counter per line.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Stats {
+    /// The number of bunyan records read.
+    pub records: usize,
+    /// The number of records that passed the filters.
+    pub matched: usize,
+    /// The number of lines that were not bunyan records.
+    pub non_records: usize,
+    /// The number of records read, by level.
+    pub levels: BTreeMap<u8, usize>,
+}
+
+impl std::fmt::Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "records: {}", self.records)?;
+        writeln!(f, "non-record lines: {}", self.non_records)?;
+        let count = |level: u8| self.levels.get(&level).copied().unwrap_or_default();
+        for (name, level) in NAMED_LOG_LEVELS.iter() {
+            writeln!(f, "{}: {}", name, count(*level as u8))?;
+        }
+        let other: usize = self
+            .levels
+            .iter()
+            .filter(|(level, _)| !NAMED_LOG_LEVELS.iter().any(|(_, l)| *l as u8 == **level))
+            .map(|(_, count)| count)
+            .sum();
+        writeln!(f, "other: {}", other)
+    }
+}
@@ -1,22 +1,319 @@
-use crate::record::LogRecord;
-use crate::Format;
-use std::io::BufRead;
+use super::compression::decompress;
+use super::interrupt::Interruptible;
+use super::json::{is_json_array, parse_array, process_array, process_values};
+use super::lenient::join_split_lines;
+use super::output::{ContextOutput, GroupedOutput, Output, SortedOutput};
+use crate::format::{format_parsed_line, format_record};
+use crate::record::{paint_into, parse_error, ParsedLine};
+use crate::{FormatOptions, FormatState, Stats};
+use std::collections::VecDeque;
+use std::io::{BufRead, Write};
 
-pub fn process_stdin(format: Format, level_filter: u8, strict: bool, utc: bool) {
+pub fn process_stdin<W: Write>(
+    output: &mut W,
+    options: &FormatOptions,
+    stats: &mut Stats,
+) -> std::io::Result<()> {
     let stdin = std::io::stdin();
-    for line in stdin.lock().lines() {
-        let line = line.unwrap();
-        match serde_json::from_str::<LogRecord>(&line) {
-            Ok(r) => {
-                if r.level >= level_filter {
-                    print!("{}", r.format(format, utc))
-                }
-            }
-            Err(_) => {
-                if !strict {
-                    println!("{}", line)
-                }
+    process_input(
+        decompress(Interruptible(stdin.lock()), None)?,
+        output,
+        options,
+        stats,
+    )
+}
+
+/// Format `input` line by line, flushing `output` after every line to keep interactive
+/// tails (e.g. `tail -f app.log | bunyan`) responsive.
+///
+/// If `input` is a single JSON array of records, each of its elements is formatted instead.
+/// In multi-line mode, the JSON values in `input` are formatted regardless of line boundaries.
+/// In raw mode, `input` is copied to `output` as is.
+pub fn process_input<R: BufRead, W: Write>(
+    input: R,
+    output: &mut W,
+    options: &FormatOptions,
+    stats: &mut Stats,
+) -> std::io::Result<()> {
+    process_input_with_state(input, output, options, &mut FormatState::new(), stats)
+}
+
+/// Like `process_input`, after the records rendered before with `state`.
+pub(super) fn process_input_with_state<R: BufRead, W: Write>(
+    input: R,
+    output: &mut W,
+    options: &FormatOptions,
+    state: &mut FormatState,
+    stats: &mut Stats,
+) -> std::io::Result<()> {
+    if options.raw {
+        return process_raw_input(input, output);
+    }
+    if let Some(field) = &options.group_by {
+        let mut grouped = GroupedOutput::new(output, field);
+        process_input_with_context(input, &mut grouped, options, state, stats)?;
+        return grouped.finish(options);
+    }
+    match options.sort_window {
+        Some(window) => {
+            let mut sorted = SortedOutput::new(output, window);
+            process_input_with_context(input, &mut sorted, options, state, stats)?;
+            sorted.finish()
+        }
+        None => process_input_with_context(input, output, options, state, stats),
+    }
+}
+
+/// Format `input`, showing the context of the records that passed the filters, if requested.
+pub(super) fn process_input_with_context<R: BufRead, O: Output>(
+    input: R,
+    output: &mut O,
+    options: &FormatOptions,
+    state: &mut FormatState,
+    stats: &mut Stats,
+) -> std::io::Result<()> {
+    if !options.has_context() {
+        return process_unsorted_input(input, output, options, state, stats);
+    }
+    let mut context = ContextOutput::new(output, options.before_context, options.after_context);
+    process_unsorted_input(input, &mut context, options, state, stats)?;
+    context.finish()
+}
+
+fn process_unsorted_input<R: BufRead, O: Output>(
+    mut input: R,
+    output: &mut O,
+    options: &FormatOptions,
+    state: &mut FormatState,
+    stats: &mut Stats,
+) -> std::io::Result<()> {
+    skip_bom(&mut input)?;
+    if is_json_array(&mut input)? {
+        // Whether it is an array can only be told once it has been read up to its end.
+        let mut document = Vec::new();
+        input.read_to_end(&mut document)?;
+        return match parse_array(&document) {
+            Some(elements) => process_array(elements, output, options, state, stats),
+            None => process_lines(&document[..], output, options, state, stats),
+        };
+    }
+    process_lines(input, output, options, state, stats)
+}
+
+/// Format `input` line by line, or value by value in multi-line mode.
+fn process_lines<R: BufRead, O: Output>(
+    mut input: R,
+    output: &mut O,
+    options: &FormatOptions,
+    state: &mut FormatState,
+    stats: &mut Stats,
+) -> std::io::Result<()> {
+    if options.multiline {
+        return process_values(input, output, options, state, stats);
+    }
+    let mut buffer = Vec::new();
+    let mut formatted = String::new();
+    let mut line_number = 0;
+    // The lines read ahead in lenient mode that turned out not to be part of a record.
+    let mut pending = VecDeque::new();
+    loop {
+        let mut line = match pending.pop_front() {
+            Some(line) => line,
+            None => match next_line(&mut input, &mut buffer)? {
+                Some(line) => line,
+                None => return Ok(()),
+            },
+        };
+        line_number += 1;
+        // Records joined from several lines are numbered after their first line.
+        let first_line_number = line_number;
+        if options.lenient {
+            let joined;
+            (line, joined) = join_split_lines(line, &mut input, &mut buffer, &mut pending)?;
+            line_number += joined;
+        }
+        if process_line(
+            &line,
+            first_line_number,
+            &mut formatted,
+            output,
+            options,
+            state,
+            stats,
+        )? {
+            return Ok(());
+        }
+    }
+}
+
+/// Copy `input` to `output` byte for byte, flushing after every line.
+fn process_raw_input<R: BufRead, W: Write>(mut input: R, output: &mut W) -> std::io::Result<()> {
+    let mut buffer = Vec::new();
+    loop {
+        buffer.clear();
+        if input.read_until(b'\n', &mut buffer)? == 0 {
+            return Ok(());
+        }
+        output.write_all(&buffer)?;
+        output.flush()?;
+    }
+}
+
+/// Skip the UTF-8 byte order mark at the start of `input`, if any: some tools start their
+/// exports with one.
+pub(super) fn skip_bom<R: BufRead>(input: &mut R) -> std::io::Result<usize> {
+    const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+    if input.fill_buf()?.starts_with(BOM) {
+        input.consume(BOM.len());
+        return Ok(BOM.len());
+    }
+    Ok(0)
+}
+
+/// Read the next line of `input`, without its newline.
+///
+/// The last line is returned even if it does not end with a newline, e.g. if the input was
+/// truncated mid-write. Invalid UTF-8 (e.g. a character cut in half) is replaced instead of
+/// being an error: such lines are handled like any other line that is not a record.
+pub(super) fn next_line<R: BufRead>(
+    input: &mut R,
+    buffer: &mut Vec<u8>,
+) -> std::io::Result<Option<String>> {
+    buffer.clear();
+    if input.read_until(b'\n', buffer)? == 0 {
+        return Ok(None);
+    }
+    if buffer.ends_with(b"\n") {
+        buffer.pop();
+    }
+    Ok(Some(String::from_utf8_lossy(buffer).into_owned()))
+}
+
+/// Interpret the `line_number`-th line of an input, returning its content along with it.
+///
+/// Unless in strict mode, the first line is a record if it becomes one once what comes before
+/// the first `{` is dropped: some tools start their exports with stray bytes.
+pub(super) fn parse_line<'a>(
+    line: &'a str,
+    line_number: usize,
+    options: &FormatOptions,
+) -> (&'a str, ParsedLine<'a>) {
+    // Lines of logs written on Windows end with "\r\n": the "\r" is not part of their content.
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    let parsed = ParsedLine::parse(line, options);
+    if line_number != 1 || options.strict || matches!(parsed, ParsedLine::Record(_)) {
+        return (line, parsed);
+    }
+    match line.find('{').filter(|start| *start > 0) {
+        Some(start) => match ParsedLine::parse(&line[start..], options) {
+            record @ ParsedLine::Record(_) => (&line[start..], record),
+            _ => (line, parsed),
+        },
+        None => (line, parsed),
+    }
+}
+
+/// Format a single line of input, flushing `output` afterwards.
+///
+/// `line_number` is the 1-based position of the line in its input, see
+/// `FormatOptions::line_numbers`.
+/// `state` is shared by the lines rendered relative to each other, see `FormatState`.
+/// `buffer` is where the line is rendered: it is cleared first, so that the same buffer can be
+/// reused for all lines.
+/// It returns `true` if the rest of the input should not be read, see
+/// `FormatOptions::assume_sorted` and `FormatOptions::max_count`.
+pub(crate) fn process_line<O: Output>(
+    line: &str,
+    line_number: usize,
+    buffer: &mut String,
+    output: &mut O,
+    options: &FormatOptions,
+    state: &mut FormatState,
+    stats: &mut Stats,
+) -> std::io::Result<bool> {
+    buffer.clear();
+    if options.raw {
+        buffer.push_str(line);
+        buffer.push('\n');
+        output.write_formatted(buffer, None)?;
+        output.flush_formatted()?;
+        return Ok(false);
+    }
+    let (line, parsed) = parse_line(line, line_number, options);
+    process_parsed_line(
+        line,
+        &parsed,
+        line_number,
+        buffer,
+        output,
+        options,
+        state,
+        stats,
+    )
+}
+
+/// Like `process_line`, for a line that has already been parsed, see `parse_line`.
+///
+/// `FormatOptions::raw` is up to the caller: the line is processed as parsed regardless.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn process_parsed_line<O: Output>(
+    line: &str,
+    parsed: &ParsedLine,
+    line_number: usize,
+    buffer: &mut String,
+    output: &mut O,
+    options: &FormatOptions,
+    state: &mut FormatState,
+    stats: &mut Stats,
+) -> std::io::Result<bool> {
+    buffer.clear();
+    if options.debug_parse && !matches!(parsed, ParsedLine::Record(_)) {
+        if let Some(error) = parse_error(line, options) {
+            eprintln!("bunyan: line {}: {}", line_number, error);
+        }
+    }
+    if options.line_numbers && matches!(parsed, ParsedLine::Record(_)) {
+        // Dropped along with the rest of the buffer if the record is skipped.
+        let prefix = format!("{}: ", line_number);
+        paint_into(buffer, &prefix, options.theme.line_number, options);
+    }
+    let written =
+        format_parsed_line(line, parsed, options, state, buffer).map_err(std::io::Error::other)?;
+    match parsed {
+        ParsedLine::Record(record) => {
+            stats.records += 1;
+            *stats.levels.entry(record.level).or_default() += 1;
+            if written {
+                stats.matched += 1;
             }
         }
+        ParsedLine::Json | ParsedLine::Text => stats.non_records += 1,
+    }
+    if options.validate {
+        let problem = match parsed {
+            ParsedLine::Record(_) => None,
+            ParsedLine::Json => Some("not a bunyan record"),
+            ParsedLine::Text => Some("not JSON"),
+        };
+        if let Some(problem) = problem {
+            eprintln!("bunyan: line {}: {}", line_number, problem);
+        }
+        return Ok(false);
+    }
+    if written && !options.quiet {
+        match parsed {
+            ParsedLine::Record(record) => output.write_formatted_record(buffer, record)?,
+            ParsedLine::Json | ParsedLine::Text => output.write_formatted(buffer, None)?,
+        }
+    } else if let (ParsedLine::Record(record), true) = (parsed, options.has_context()) {
+        // The record did not pass the filters: nothing but its line number has been rendered.
+        if !options.quiet
+            && format_record(record, options, state, buffer).map_err(std::io::Error::other)?
+        {
+            output.write_context(buffer, record.time)?;
+        }
     }
+    output.flush_formatted()?;
+    Ok(options.ends_input(parsed) || options.is_done(stats))
 }
@@ -0,0 +1,82 @@
+use super::follow::POLL_INTERVAL;
+use super::interrupt::Interruptible;
+use super::stdin::process_input_with_state;
+use crate::{interrupted, FormatOptions, FormatState, Stats};
+use std::io::{BufReader, Read, Write};
+use std::net::{TcpListener, ToSocketAddrs};
+
+/// Listen for TCP connections on `address` and format the lines sent by clients, one connection
+/// at a time.
+///
+/// Once a client disconnects, the next connection is accepted: it only returns once
+/// `FormatOptions::max_count` records passed the filters, on Ctrl-C (see `catch_interrupts`), or
+/// on errors binding the socket or writing the output.
+pub fn listen<A: ToSocketAddrs, W: Write>(
+    address: A,
+    output: &mut W,
+    options: &FormatOptions,
+    stats: &mut Stats,
+) -> std::io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    // The actual address is useful when binding to port 0, i.e. any free port.
+    eprintln!("bunyan: listening on {}", listener.local_addr()?);
+
+    // Waiting for connections is polled: Ctrl-C does not interrupt it otherwise.
+    listener.set_nonblocking(true)?;
+    // Connections are rendered one after the other, as a single input.
+    let mut state = FormatState::new();
+    while !interrupted() {
+        let accepted = listener
+            .accept()
+            .and_then(|(stream, _)| stream.set_nonblocking(false).map(|_| stream));
+        let stream = match accepted {
+            Ok(stream) => stream,
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                std::thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+            Err(e) => {
+                eprintln!("bunyan: failed to accept a connection: {}", e);
+                continue;
+            }
+        };
+        let peer = stream
+            .peer_addr()
+            .map(|peer| peer.to_string())
+            .unwrap_or_else(|_| "client".into());
+        let mut connection = Connection {
+            stream,
+            failed: false,
+        };
+        let input = BufReader::new(Interruptible(&mut connection));
+        if let Err(e) = process_input_with_state(input, output, options, &mut state, stats) {
+            // Errors on the connection (e.g. a reset) only end it, while the other ones (e.g. a
+            // full disk, or `bunyan --listen ... | head`) are fatal.
+            if !connection.failed {
+                return Err(e);
+            }
+            eprintln!("bunyan: {}: {}", peer, e);
+        }
+        if options.is_done(stats) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// A client's stream, recording whether reading from it failed.
+struct Connection<R> {
+    stream: R,
+    failed: bool,
+}
+
+impl<R: Read> Read for Connection<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let result = self.stream.read(buf);
+        if let Err(e) = &result {
+            // Retried by the readers.
+            self.failed = self.failed || e.kind() != std::io::ErrorKind::Interrupted;
+        }
+        result
+    }
+}
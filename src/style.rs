@@ -1,10 +1,30 @@
+use chrono::SecondsFormat;
+use itertools::Itertools;
 use std::str::FromStr;
 
 /// Supported output formats.
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
 pub enum Format {
-    /// Prettified JSON.
+    /// The time, level, origin and message of the record on one line, followed by its extra
+    /// fields (inline or as indented details below).
+    #[default]
     Long,
+    /// Compact single-line records: no hostname, logger name or pid, time-of-day only.
+    Short,
+    /// The record re-serialized as JSON, indented by the given number of spaces.
+    /// An indentation of 0 produces compact single-line JSON.
+    Json(usize),
+    /// The record re-serialized as single-line JSON, i.e. a normalized bunyan log.
+    /// Lines that are not bunyan records are dropped.
+    Bunyan,
+    /// The record as parsed, using Rust's pretty-printed `Debug` representation.
+    Inspect,
+    /// A single line of space-separated `key=value` pairs, see <https://brandur.org/logfmt>.
+    Logfmt,
+    /// Just the level and the message of the records.
+    Simple,
+    /// Records as rows of aligned columns, see `FormatOptions::columns`.
+    Table,
 }
 
 impl FromStr for Format {
@@ -13,7 +33,772 @@ impl FromStr for Format {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_str() {
             "long" => Ok(Format::Long),
+            "short" => Ok(Format::Short),
+            "json" => Ok(Format::Json(2)),
+            "bunyan" => Ok(Format::Bunyan),
+            "inspect" => Ok(Format::Inspect),
+            "logfmt" => Ok(Format::Logfmt),
+            "simple" => Ok(Format::Simple),
+            "table" => Ok(Format::Table),
+            s if s.starts_with("json-") => s["json-".len()..]
+                .parse()
+                .map(Format::Json)
+                .map_err(|_| anyhow::anyhow!(format!("Invalid format value: '{}'", s))),
             _ => Err(anyhow::anyhow!(format!("Invalid format value: '{}'", s))),
         }
     }
 }
+
+/// Supported input formats.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum InputFormat {
+    /// Bunyan records, as well as similar formats (e.g. pino's, with numeric timestamps).
+    #[default]
+    Auto,
+    /// Bunyan records only: their `time` must be an ISO 8601 string.
+    Bunyan,
+    /// [pino](https://getpino.io) records only: their `time` must be an integer, the number of
+    /// milliseconds since the epoch. Their levels are the same as bunyan's.
+    Pino,
+    /// [GELF](https://go2docs.graylog.org/current/getting_in_log_data/gelf.html) messages, e.g.
+    /// Graylog exports: their fields are mapped to the ones of bunyan records.
+    Gelf,
+}
+
+impl FromStr for InputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(InputFormat::Auto),
+            "bunyan" => Ok(InputFormat::Bunyan),
+            "pino" => Ok(InputFormat::Pino),
+            "gelf" => Ok(InputFormat::Gelf),
+            _ => Err(anyhow::anyhow!(format!(
+                "Invalid input format value: '{}'",
+                s
+            ))),
+        }
+    }
+}
+
+/// How timestamps are displayed.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TimeMode {
+    /// The local timezone of the machine running `bunyan`.
+    Local,
+    /// UTC, as recorded in the `time` field of bunyan records.
+    Utc,
+    /// The time elapsed since the first record, in seconds (e.g. `+1.234`), see
+    /// `FormatState`. Custom time formats do not apply.
+    Relative,
+}
+
+impl FromStr for TimeMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "local" => Ok(TimeMode::Local),
+            "utc" => Ok(TimeMode::Utc),
+            "relative" => Ok(TimeMode::Relative),
+            _ => Err(anyhow::anyhow!(format!("Invalid time value: '{}'", s))),
+        }
+    }
+}
+
+/// The sub-second precision of displayed timestamps.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TimePrecision {
+    /// Whole seconds (`s`).
+    Seconds,
+    /// Milliseconds (`ms`).
+    Millis,
+    /// Microseconds (`us`).
+    Micros,
+    /// Nanoseconds (`ns`).
+    Nanos,
+}
+
+impl TimePrecision {
+    pub(crate) fn seconds_format(self) -> SecondsFormat {
+        match self {
+            TimePrecision::Seconds => SecondsFormat::Secs,
+            TimePrecision::Millis => SecondsFormat::Millis,
+            TimePrecision::Micros => SecondsFormat::Micros,
+            TimePrecision::Nanos => SecondsFormat::Nanos,
+        }
+    }
+
+    /// The number of digits of the fractional part of the seconds.
+    pub(crate) fn digits(self) -> usize {
+        match self {
+            TimePrecision::Seconds => 0,
+            TimePrecision::Millis => 3,
+            TimePrecision::Micros => 6,
+            TimePrecision::Nanos => 9,
+        }
+    }
+
+    /// The `strftime` specifier for the fractional part of the seconds.
+    pub(crate) fn strftime_fraction(self) -> &'static str {
+        match self {
+            TimePrecision::Seconds => "",
+            TimePrecision::Millis => "%.3f",
+            TimePrecision::Micros => "%.6f",
+            TimePrecision::Nanos => "%.9f",
+        }
+    }
+}
+
+impl FromStr for TimePrecision {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "s" => Ok(TimePrecision::Seconds),
+            "ms" => Ok(TimePrecision::Millis),
+            "us" => Ok(TimePrecision::Micros),
+            "ns" => Ok(TimePrecision::Nanos),
+            _ => Err(anyhow::anyhow!(format!(
+                "Invalid time precision value: '{}'",
+                s
+            ))),
+        }
+    }
+}
+
+/// How many colors the terminal supports.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ColorDepth {
+    /// The 16 standard ANSI colors (`16`).
+    Ansi16,
+    /// The 256 colors of xterm (`256`).
+    Ansi256,
+    /// 24-bit RGB colors (`truecolor`).
+    TrueColor,
+}
+
+impl ColorDepth {
+    /// Probe the capabilities of the terminal using the `COLORTERM` and `TERM` environment
+    /// variables.
+    pub fn from_env() -> Self {
+        let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+        let term = std::env::var("TERM").unwrap_or_default();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            ColorDepth::TrueColor
+        } else if term.contains("256color") {
+            ColorDepth::Ansi256
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
+}
+
+impl FromStr for ColorDepth {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "16" => Ok(ColorDepth::Ansi16),
+            "256" => Ok(ColorDepth::Ansi256),
+            "truecolor" | "24bit" => Ok(ColorDepth::TrueColor),
+            _ => Err(anyhow::anyhow!(format!(
+                "Invalid color depth value: '{}'",
+                s
+            ))),
+        }
+    }
+}
+
+/// A color given on the command line: a color name (e.g. `green`, `bright blue`), an `r,g,b`
+/// triple (e.g. `255,128,0`) or `none`, for no color at all.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ColorChoice(pub Option<colored::Color>);
+
+impl FromStr for ColorChoice {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("none") {
+            Ok(ColorChoice(None))
+        } else {
+            parse_color(s).map(|color| ColorChoice(Some(color)))
+        }
+    }
+}
+
+/// Parse either a color name or an `r,g,b` triple.
+pub(crate) fn parse_color(s: &str) -> Result<colored::Color, anyhow::Error> {
+    let invalid = || anyhow::anyhow!(format!("Invalid color value: '{}'", s));
+    if s.contains(',') {
+        let components = s
+            .split(',')
+            .map(|c| c.trim().parse::<u8>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| invalid())?;
+        match components[..] {
+            [r, g, b] => Ok(colored::Color::TrueColor { r, g, b }),
+            _ => Err(invalid()),
+        }
+    } else {
+        s.parse().map_err(|_| invalid())
+    }
+}
+
+/// How the messages of records are lined up, see `FormatOptions::align`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Align {
+    /// Messages start at this column, unless the record's prefix is wider.
+    Column(usize),
+    /// Messages start after the widest prefix seen so far (`auto`, see `FormatState`): they
+    /// line up as soon as the widest record has been shown.
+    Widest,
+}
+
+impl FromStr for Align {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(Align::Widest),
+            s => s
+                .parse()
+                .map(Align::Column)
+                .map_err(|_| anyhow::anyhow!(format!("Invalid align value: '{}'", s))),
+        }
+    }
+}
+
+/// A column of the table output format, see `Columns`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Column {
+    /// `time`, rendered like in the long format.
+    Time,
+    /// `level`
+    Level,
+    /// `name`, the name of the logger.
+    Name,
+    /// `hostname`
+    Hostname,
+    /// `pid`
+    Pid,
+    /// `msg`, cut to fit within `FormatOptions::max_width`.
+    Msg,
+    /// An extra field, e.g. `extras.user.id` for the `id` of the `user` field.
+    Extra(String),
+}
+
+impl FromStr for Column {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "time" => Ok(Column::Time),
+            "level" => Ok(Column::Level),
+            "name" => Ok(Column::Name),
+            "hostname" => Ok(Column::Hostname),
+            "pid" => Ok(Column::Pid),
+            "msg" => Ok(Column::Msg),
+            _ => match s.strip_prefix("extras.") {
+                Some(path) if !path.is_empty() => Ok(Column::Extra(path.to_owned())),
+                _ => Err(anyhow::anyhow!(format!("Invalid column value: '{}'", s))),
+            },
+        }
+    }
+}
+
+/// The columns of the table output format, e.g. `time,level,name,msg`.
+///
+/// Each column is as wide as its widest cell so far (see `FormatState`): columns line up as
+/// soon as the widest records have been shown.
+/// The default columns are the time, level, pid and message of records.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Columns {
+    columns: Vec<Column>,
+}
+
+impl Columns {
+    /// These columns, in order.
+    pub fn new(columns: Vec<Column>) -> Self {
+        Columns { columns }
+    }
+
+    /// The columns, in order.
+    pub fn iter(&self) -> impl Iterator<Item = &Column> {
+        self.columns.iter()
+    }
+}
+
+impl Default for Columns {
+    fn default() -> Self {
+        Columns::new(vec![Column::Time, Column::Level, Column::Pid, Column::Msg])
+    }
+}
+
+impl FromStr for Columns {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let columns = s
+            .split(',')
+            .map(|column| column.trim().parse())
+            .collect::<Result<_, _>>()?;
+        Ok(Columns::new(columns))
+    }
+}
+
+/// How colors and text attributes are rendered.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ColorEscape {
+    /// ANSI escape sequences, for terminals (`ansi`).
+    Ansi,
+    /// Readable tokens (`visible`), e.g. `<bold+red>boom</>`, to debug colors or write tests
+    /// about them.
+    Visible,
+}
+
+impl FromStr for ColorEscape {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ansi" => Ok(ColorEscape::Ansi),
+            "visible" => Ok(ColorEscape::Visible),
+            _ => Err(anyhow::anyhow!(format!(
+                "Invalid color escape value: '{}'",
+                s
+            ))),
+        }
+    }
+}
+
+/// When string values of extra fields are quoted, see `FormatOptions::quote_style`.
+///
+/// Quoted values are escaped like JSON strings.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum QuoteStyle {
+    /// Only values that would be ambiguous otherwise (`minimal`), e.g. empty ones or ones with
+    /// spaces.
+    #[default]
+    Minimal,
+    /// All string values (`always`).
+    Always,
+    /// No values (`never`), except for those with newlines in the outputs that keep each record
+    /// on a single line.
+    Never,
+}
+
+impl FromStr for QuoteStyle {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "minimal" => Ok(QuoteStyle::Minimal),
+            "always" => Ok(QuoteStyle::Always),
+            "never" => Ok(QuoteStyle::Never),
+            _ => Err(anyhow::anyhow!(format!(
+                "Invalid quote style value: '{}'",
+                s
+            ))),
+        }
+    }
+}
+
+/// The style of the FATAL level, overriding the one of the theme.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FatalStyle {
+    /// Reversed video (`reversed`), as in node-bunyan.
+    Reversed,
+    /// Bold red text (`bold-red`).
+    BoldRed,
+    /// White text on a red background (`white-on-red`).
+    WhiteOnRed,
+}
+
+impl FatalStyle {
+    /// The paint rendering this style.
+    pub fn paint(self) -> Paint {
+        use colored::Color;
+        match self {
+            FatalStyle::Reversed => Paint {
+                reversed: true,
+                ..Paint::PLAIN
+            },
+            FatalStyle::BoldRed => Paint {
+                bold: true,
+                ..Paint::color(Color::Red)
+            },
+            FatalStyle::WhiteOnRed => Paint {
+                background: Some(Color::Red),
+                ..Paint::color(Color::White)
+            },
+        }
+    }
+}
+
+impl FromStr for FatalStyle {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "reversed" => Ok(FatalStyle::Reversed),
+            "bold-red" => Ok(FatalStyle::BoldRed),
+            "white-on-red" => Ok(FatalStyle::WhiteOnRed),
+            _ => Err(anyhow::anyhow!(format!(
+                "Invalid fatal style value: '{}'",
+                s
+            ))),
+        }
+    }
+}
+
+/// How a piece of output is rendered: colors and text attributes.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Paint {
+    /// The foreground color, if any.
+    pub color: Option<colored::Color>,
+    /// The background color, if any.
+    pub background: Option<colored::Color>,
+    pub bold: bool,
+    pub dimmed: bool,
+    pub reversed: bool,
+}
+
+impl Paint {
+    const PLAIN: Paint = Paint {
+        color: None,
+        background: None,
+        bold: false,
+        dimmed: false,
+        reversed: false,
+    };
+    const BOLD: Paint = Paint {
+        bold: true,
+        ..Paint::PLAIN
+    };
+
+    const fn color(color: colored::Color) -> Self {
+        Paint {
+            color: Some(color),
+            ..Paint::PLAIN
+        }
+    }
+
+    const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Paint::color(colored::Color::TrueColor { r, g, b })
+    }
+
+    /// Append `s` to `buffer`, wrapped in the escape sequences rendering this paint on a
+    /// terminal with the specified color depth.
+    ///
+    /// `colored` only emits 24-bit colors if `COLORTERM` says they are supported: escape
+    /// sequences are written by hand for the color depth to be overridden.
+    pub(crate) fn write(&self, buffer: &mut String, s: &str, depth: ColorDepth) {
+        if *self == Paint::PLAIN || s.is_empty() {
+            buffer.push_str(s);
+            return;
+        }
+        let codes = [
+            self.bold.then(|| "1".into()),
+            self.dimmed.then(|| "2".into()),
+            self.reversed.then(|| "7".into()),
+            self.color.map(|color| foreground_code(color, depth)),
+            self.background.map(|color| background_code(color, depth)),
+        ];
+        buffer.push_str("\u{1b}[");
+        buffer.push_str(&codes.iter().flatten().join(";"));
+        buffer.push('m');
+        buffer.push_str(s);
+        buffer.push_str("\u{1b}[0m");
+    }
+
+    /// Like `write`, with readable tokens instead of escape sequences, see
+    /// `ColorEscape::Visible`. Colors are not approximated.
+    pub(crate) fn write_visible(&self, buffer: &mut String, s: &str) {
+        if *self == Paint::PLAIN || s.is_empty() {
+            buffer.push_str(s);
+            return;
+        }
+        let tokens = [
+            self.bold.then(|| "bold".into()),
+            self.dimmed.then(|| "dimmed".into()),
+            self.reversed.then(|| "reversed".into()),
+            self.color.map(color_name),
+            self.background
+                .map(|color| format!("on {}", color_name(color)).into()),
+        ];
+        buffer.push('<');
+        buffer.push_str(&tokens.iter().flatten().join("+"));
+        buffer.push('>');
+        buffer.push_str(s);
+        buffer.push_str("</>");
+    }
+}
+
+/// The name of a color, as accepted by `parse_color`.
+fn color_name(color: colored::Color) -> std::borrow::Cow<'static, str> {
+    use colored::Color::*;
+    match color {
+        Black => "black".into(),
+        Red => "red".into(),
+        Green => "green".into(),
+        Yellow => "yellow".into(),
+        Blue => "blue".into(),
+        Magenta => "magenta".into(),
+        Cyan => "cyan".into(),
+        White => "white".into(),
+        BrightBlack => "bright black".into(),
+        BrightRed => "bright red".into(),
+        BrightGreen => "bright green".into(),
+        BrightYellow => "bright yellow".into(),
+        BrightBlue => "bright blue".into(),
+        BrightMagenta => "bright magenta".into(),
+        BrightCyan => "bright cyan".into(),
+        BrightWhite => "bright white".into(),
+        TrueColor { r, g, b } => format!("{},{},{}", r, g, b).into(),
+    }
+}
+
+impl From<Option<colored::Color>> for Paint {
+    fn from(color: Option<colored::Color>) -> Self {
+        Paint {
+            color,
+            ..Paint::PLAIN
+        }
+    }
+}
+
+/// The SGR parameter selecting a foreground color, approximating 24-bit colors if needed.
+fn foreground_code(color: colored::Color, depth: ColorDepth) -> std::borrow::Cow<'static, str> {
+    match (color, depth) {
+        (colored::Color::TrueColor { r, g, b }, ColorDepth::TrueColor) => {
+            format!("38;2;{};{};{}", r, g, b).into()
+        }
+        (colored::Color::TrueColor { r, g, b }, ColorDepth::Ansi256) => {
+            format!("38;5;{}", closest_ansi256(r, g, b)).into()
+        }
+        (colored::Color::TrueColor { r, g, b }, ColorDepth::Ansi16) => {
+            closest_ansi16(r, g, b).to_fg_str()
+        }
+        (color, _) => color.to_fg_str(),
+    }
+}
+
+/// The SGR parameter selecting a background color, approximating 24-bit colors if needed.
+fn background_code(color: colored::Color, depth: ColorDepth) -> std::borrow::Cow<'static, str> {
+    match (color, depth) {
+        (colored::Color::TrueColor { r, g, b }, ColorDepth::TrueColor) => {
+            format!("48;2;{};{};{}", r, g, b).into()
+        }
+        (colored::Color::TrueColor { r, g, b }, ColorDepth::Ansi256) => {
+            format!("48;5;{}", closest_ansi256(r, g, b)).into()
+        }
+        (colored::Color::TrueColor { r, g, b }, ColorDepth::Ansi16) => {
+            closest_ansi16(r, g, b).to_bg_str()
+        }
+        (color, _) => color.to_bg_str(),
+    }
+}
+
+/// The closest color of the xterm palette: grays are taken from the grayscale ramp (232 to
+/// 255), other colors from the 6x6x6 cube (16 to 231).
+fn closest_ansi256(r: u8, g: u8, b: u8) -> u8 {
+    if r == g && g == b {
+        return match r {
+            0..=3 => 16,
+            247..=255 => 231,
+            v => 232 + ((v.saturating_sub(8) + 5) / 10).min(23),
+        };
+    }
+    let level = |v: u8| match v {
+        0..=47 => 0,
+        48..=114 => 1,
+        v => (v - 35) / 40,
+    };
+    16 + 36 * level(r) + 6 * level(g) + level(b)
+}
+
+/// The closest of the 16 ANSI colors, as rendered by xterm.
+fn closest_ansi16(r: u8, g: u8, b: u8) -> colored::Color {
+    use colored::Color::*;
+    let palette = [
+        (Black, (0, 0, 0)),
+        (Red, (205, 0, 0)),
+        (Green, (0, 205, 0)),
+        (Yellow, (205, 205, 0)),
+        (Blue, (0, 0, 238)),
+        (Magenta, (205, 0, 205)),
+        (Cyan, (0, 205, 205)),
+        (White, (229, 229, 229)),
+        (BrightBlack, (127, 127, 127)),
+        (BrightRed, (255, 0, 0)),
+        (BrightGreen, (0, 255, 0)),
+        (BrightYellow, (255, 255, 0)),
+        (BrightBlue, (92, 92, 255)),
+        (BrightMagenta, (255, 0, 255)),
+        (BrightCyan, (0, 255, 255)),
+        (BrightWhite, (255, 255, 255)),
+    ];
+    let distance = |(pr, pg, pb): (i32, i32, i32)| {
+        (pr - r as i32).pow(2) + (pg - g as i32).pow(2) + (pb - b as i32).pow(2)
+    };
+    palette
+        .iter()
+        .min_by_key(|(_, rgb)| distance(*rgb))
+        .map(|(color, _)| *color)
+        .unwrap_or(White)
+}
+
+/// A coherent set of paints for the colorized output, see `FormatOptions::theme`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ColorTheme {
+    pub trace: Paint,
+    pub debug: Paint,
+    pub info: Paint,
+    pub warn: Paint,
+    pub error: Paint,
+    pub fatal: Paint,
+    /// The message of the records.
+    pub message: Paint,
+    /// The parts of the message matching `FormatOptions::grep`.
+    pub highlight: Paint,
+    /// The source location of the log call.
+    pub src: Paint,
+    /// The names of the extra fields.
+    pub key: Paint,
+    /// String values of inline extra fields, see `FormatOptions::color_values`.
+    pub string: Paint,
+    /// Number values of inline extra fields, see `FormatOptions::color_values`.
+    pub number: Paint,
+    /// `true` values of inline extra fields.
+    pub bool_true: Paint,
+    /// `false` values of inline extra fields.
+    pub bool_false: Paint,
+    /// Null values of inline extra fields.
+    pub null: Paint,
+    /// Durations above `FormatOptions::slow_threshold`.
+    pub slow: Paint,
+    /// The marker of lines that are not records, see `FormatOptions::raw_marker`.
+    pub raw_marker: Paint,
+    /// The line numbers of records, see `FormatOptions::line_numbers`.
+    pub line_number: Paint,
+    /// The headers of groups of records, see `FormatOptions::group_by`.
+    pub group: Paint,
+}
+
+impl ColorTheme {
+    /// Distinct hues from the [Solarized](https://ethanschoonover.com/solarized/) palette.
+    pub fn solarized() -> Self {
+        ColorTheme {
+            trace: Paint::rgb(88, 110, 117),
+            debug: Paint::rgb(108, 113, 196),
+            info: Paint::rgb(38, 139, 210),
+            warn: Paint::rgb(181, 137, 0),
+            error: Paint::rgb(220, 50, 47),
+            fatal: Paint {
+                bold: true,
+                ..Paint::rgb(211, 54, 130)
+            },
+            message: Paint::rgb(42, 161, 152),
+            highlight: Paint {
+                bold: true,
+                ..Paint::rgb(203, 75, 22)
+            },
+            src: Paint::rgb(133, 153, 0),
+            key: Paint::BOLD,
+            string: Paint::rgb(133, 153, 0),
+            number: Paint::rgb(203, 75, 22),
+            bool_true: Paint::rgb(133, 153, 0),
+            bool_false: Paint::rgb(220, 50, 47),
+            null: Paint::rgb(88, 110, 117),
+            slow: Paint::rgb(220, 50, 47),
+            raw_marker: Paint::rgb(88, 110, 117),
+            line_number: Paint::rgb(88, 110, 117),
+            group: Paint::BOLD,
+        }
+    }
+
+    /// No colors at all: levels, keys and highlights are bold.
+    pub fn mono() -> Self {
+        ColorTheme {
+            trace: Paint::BOLD,
+            debug: Paint::BOLD,
+            info: Paint::BOLD,
+            warn: Paint::BOLD,
+            error: Paint::BOLD,
+            fatal: Paint::BOLD,
+            message: Paint::PLAIN,
+            highlight: Paint::BOLD,
+            src: Paint::PLAIN,
+            key: Paint::BOLD,
+            string: Paint::PLAIN,
+            number: Paint::PLAIN,
+            bool_true: Paint::PLAIN,
+            bool_false: Paint::PLAIN,
+            null: Paint::PLAIN,
+            slow: Paint::BOLD,
+            raw_marker: Paint::PLAIN,
+            line_number: Paint::PLAIN,
+            group: Paint::BOLD,
+        }
+    }
+}
+
+impl Default for ColorTheme {
+    fn default() -> Self {
+        use colored::Color;
+        ColorTheme {
+            trace: Paint::rgb(128, 128, 128),
+            debug: Paint::color(Color::Blue),
+            info: Paint::color(Color::Cyan),
+            warn: Paint::color(Color::Yellow),
+            error: Paint::color(Color::Red),
+            fatal: Paint {
+                reversed: true,
+                ..Paint::PLAIN
+            },
+            message: Paint::color(Color::Cyan),
+            highlight: Paint {
+                bold: true,
+                ..Paint::color(Color::Red)
+            },
+            src: Paint::color(Color::Green),
+            key: Paint::BOLD,
+            string: Paint::color(Color::Green),
+            number: Paint::color(Color::Yellow),
+            bool_true: Paint::color(Color::Green),
+            bool_false: Paint::color(Color::Red),
+            null: Paint {
+                dimmed: true,
+                ..Paint::PLAIN
+            },
+            slow: Paint::color(Color::Red),
+            raw_marker: Paint {
+                dimmed: true,
+                ..Paint::PLAIN
+            },
+            line_number: Paint {
+                dimmed: true,
+                ..Paint::PLAIN
+            },
+            group: Paint::BOLD,
+        }
+    }
+}
+
+impl FromStr for ColorTheme {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "default" => Ok(ColorTheme::default()),
+            "solarized" => Ok(ColorTheme::solarized()),
+            "mono" => Ok(ColorTheme::mono()),
+            _ => Err(anyhow::anyhow!(format!(
+                "Invalid color theme value: '{}'",
+                s
+            ))),
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use chrono::{DateTime, Duration, Utc};
+use std::str::FromStr;
+
+/// A bound of the time window records are filtered by (see `--since` and `--until`).
+///
+/// It is either an RFC 3339 timestamp (e.g. `2012-02-08T22:56:52Z`) or a time relative to now,
+/// in seconds, minutes, hours or days (e.g. `-10m`, `-2h`, `-1d`).
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TimeBound(pub DateTime<Utc>);
+
+impl FromStr for TimeBound {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || anyhow::anyhow!(format!("Invalid timestamp value: '{}'", s));
+        if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+            return Ok(TimeBound(time.with_timezone(&Utc)));
+        }
+        let ago = s.strip_prefix('-').ok_or_else(invalid)?;
+        let unit_start = ago
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or_else(invalid)?;
+        let amount: i64 = ago[..unit_start].parse().map_err(|_| invalid())?;
+        let duration = match &ago[unit_start..] {
+            "s" => Duration::try_seconds(amount),
+            "m" => Duration::try_minutes(amount),
+            "h" => Duration::try_hours(amount),
+            "d" => Duration::try_days(amount),
+            _ => None,
+        }
+        .ok_or_else(invalid)?;
+        Utc::now()
+            .checked_sub_signed(duration)
+            .map(TimeBound)
+            .ok_or_else(invalid)
+    }
+}
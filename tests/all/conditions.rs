@@ -0,0 +1,61 @@
+use crate::helpers::{command, get_corpus_path};
+use predicates::prelude::*;
+
+fn filter(condition: &str) -> String {
+    let input_path = get_corpus_path().join("all.log");
+
+    let mut cmd = command();
+    cmd.arg("--no-color")
+        .arg("--strict")
+        .arg("-c")
+        .arg(condition)
+        .pipe_stdin(input_path)
+        .unwrap();
+    let output = cmd.assert().success().get_output().stdout.clone();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn level_comparisons_coerce_strings() {
+    let expected = "[2012-02-08T22:56:56.856Z] FATAL: myservice/123 on example.com: My message\n";
+    assert_eq!(filter("this.level == 60"), expected);
+    assert_eq!(filter("this.level == \"60\""), expected);
+    assert_eq!(filter("this.level > '55'"), expected);
+    assert_eq!(filter("this.level === FATAL"), expected);
+    assert_eq!(filter("this.level == 'fatal'"), "");
+}
+
+#[test]
+fn conditions_on_extras() {
+    assert_eq!(
+        filter("this.five.a == \"json object\" || this.one == 'short'"),
+        "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message (one=short)
+[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message
+    five: {
+      \"a\": \"json object\"
+    }
+"
+    );
+}
+
+#[test]
+fn unknown_fields_are_undefined() {
+    assert_eq!(filter("this.missing == null"), "");
+    assert_eq!(filter("this.missing == 'x' && level == 60"), "");
+    assert_eq!(
+        filter("this.missing != 'x' && level == 60"),
+        "[2012-02-08T22:56:56.856Z] FATAL: myservice/123 on example.com: My message\n"
+    );
+}
+
+#[test]
+fn invalid_conditions() {
+    for invalid_condition in &["this.level >", "this.msg == 'unterminated", "level = 30"] {
+        let mut cmd = command();
+
+        cmd.arg("-c").arg(invalid_condition);
+        cmd.assert()
+            .failure()
+            .stderr(predicate::str::contains("Invalid condition"));
+    }
+}
@@ -0,0 +1,349 @@
+use crate::helpers::{command, get_corpus_path};
+
+fn filter(args: &[&str]) -> String {
+    let input_path = get_corpus_path().join("multi.log");
+
+    let mut cmd = command();
+    cmd.arg("--no-color")
+        .arg("-o")
+        .arg("short")
+        .args(args)
+        .pipe_stdin(input_path)
+        .unwrap();
+    let output = cmd.assert().success().get_output().stdout.clone();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn pid_filter() {
+    assert_eq!(
+        filter(&["-p", "456"]),
+        "22:56:53.856  WARN: worker on alpha\n"
+    );
+    assert_eq!(
+        filter(&["-p", "123", "--pid", "789"]),
+        "22:56:52.856  INFO: api on alpha\n22:56:54.856 ERROR: api on beta\n"
+    );
+    // All filters must pass.
+    assert_eq!(
+        filter(&["-p", "123", "-p", "789", "-l", "warn"]),
+        "22:56:54.856 ERROR: api on beta\n"
+    );
+    assert_eq!(filter(&["-p", "123", "-c", "this.level > 40"]), "");
+}
+
+#[test]
+fn time_range_filter() {
+    // Bounds are inclusive.
+    assert_eq!(
+        filter(&["--since", "2012-02-08T22:56:53.856Z"]),
+        "22:56:53.856  WARN: worker on alpha\n22:56:54.856 ERROR: api on beta\n22:56:55.856  INFO: anonymous\n"
+    );
+    assert_eq!(
+        filter(&["--until", "2012-02-08T22:56:53.856Z"]),
+        "22:56:52.856  INFO: api on alpha\n22:56:53.856  WARN: worker on alpha\n"
+    );
+    assert_eq!(
+        filter(&[
+            "--since",
+            "2012-02-08T23:56:54+01:00",
+            "--until",
+            "2012-02-08T22:56:54.856Z"
+        ]),
+        "22:56:54.856 ERROR: api on beta\n"
+    );
+    // Relative to now.
+    assert_eq!(filter(&["--since", "-1d"]), "");
+    assert_eq!(filter(&["--until", "-10m"]).lines().count(), 4);
+}
+
+#[test]
+fn invalid_time_range() {
+    for invalid in &["yesterday", "-10", "-10y", "2012-02-08"] {
+        let mut cmd = command();
+        cmd.arg("--since").arg(invalid);
+        cmd.assert()
+            .failure()
+            .stderr(predicates::str::contains("Invalid timestamp value"));
+    }
+}
+
+#[test]
+fn assume_sorted() {
+    let input_path = get_corpus_path().join("unsorted.log");
+    let until = ["-o", "short", "--until", "2012-02-08T22:56:54Z"];
+
+    let mut cmd = command();
+    cmd.args(until).pipe_stdin(&input_path).unwrap();
+    cmd.assert()
+        .success()
+        .stdout("22:56:52.856  INFO: first\n22:56:53.856  INFO: out of order\n");
+
+    // Reading stops at the first record past the end of the window.
+    let mut cmd = command();
+    cmd.args(until)
+        .arg("--assume-sorted")
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout("22:56:52.856  INFO: first\n");
+}
+
+#[test]
+fn name_filter() {
+    assert_eq!(
+        filter(&["--name", "worker"]),
+        "22:56:53.856  WARN: worker on alpha\n"
+    );
+    assert_eq!(
+        filter(&["--name", "api"]),
+        "22:56:52.856  INFO: api on alpha\n22:56:54.856 ERROR: api on beta\n"
+    );
+    assert_eq!(
+        filter(&["--name", "api", "--name", "worker", "-l", "warn"]),
+        "22:56:53.856  WARN: worker on alpha\n22:56:54.856 ERROR: api on beta\n"
+    );
+    // Exact matches only, unless asked otherwise.
+    assert_eq!(filter(&["--name", "work"]), "");
+    assert_eq!(
+        filter(&["--name", "work", "--name-substring"]),
+        "22:56:53.856  WARN: worker on alpha\n"
+    );
+    assert_eq!(filter(&["--name", "api", "-p", "456"]), "");
+}
+
+#[test]
+fn grep_filter() {
+    assert_eq!(
+        filter(&["--grep", "^api"]),
+        "22:56:52.856  INFO: api on alpha\n22:56:54.856 ERROR: api on beta\n"
+    );
+    assert_eq!(
+        filter(&["--grep", "al.ha$"]),
+        "22:56:52.856  INFO: api on alpha\n22:56:53.856  WARN: worker on alpha\n"
+    );
+    assert_eq!(filter(&["--grep", "gamma"]), "");
+    assert_eq!(
+        filter(&["--grep", "alpha", "-v"]),
+        "22:56:54.856 ERROR: api on beta\n22:56:55.856  INFO: anonymous\n"
+    );
+    assert_eq!(
+        filter(&["--grep", "alpha", "--invert-grep", "-l", "warn"]),
+        "22:56:54.856 ERROR: api on beta\n"
+    );
+}
+
+#[test]
+fn grep_highlights_matches() {
+    let mut cmd = command();
+    cmd.args(["-o", "short", "--color", "--grep", "a"])
+        .pipe_stdin(get_corpus_path().join("multi.log"))
+        .unwrap();
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let first_line = String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .next()
+        .unwrap()
+        .to_owned();
+    assert_eq!(
+        first_line,
+        "22:56:52.856 \u{1b}[36m INFO\u{1b}[0m: \u{1b}[1;31ma\u{1b}[0m\u{1b}[36mpi on \u{1b}[0m\u{1b}[1;31ma\u{1b}[0m\u{1b}[36mlph\u{1b}[0m\u{1b}[1;31ma\u{1b}[0m"
+    );
+}
+
+#[test]
+fn invalid_grep() {
+    let mut cmd = command();
+    cmd.args(["--grep", "(unclosed"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicates::str::contains("regex parse error"));
+}
+
+#[test]
+fn hostname_filter() {
+    assert_eq!(
+        filter(&["--hostname", "alpha.example.com"]),
+        "22:56:52.856  INFO: api on alpha\n22:56:53.856  WARN: worker on alpha\n"
+    );
+    assert_eq!(
+        filter(&["--hostname", "alpha.example.com", "--hostname", "beta.example.com"]),
+        "22:56:52.856  INFO: api on alpha\n22:56:53.856  WARN: worker on alpha\n22:56:54.856 ERROR: api on beta\n"
+    );
+    assert_eq!(filter(&["--hostname", "alpha"]), "");
+    assert_eq!(
+        filter(&["--hostname", "alpha.example.com", "--name", "worker"]),
+        "22:56:53.856  WARN: worker on alpha\n"
+    );
+}
+
+#[test]
+fn quiet_exit_status() {
+    let input_path = get_corpus_path().join("multi.log");
+    let cases = [
+        (vec!["-l", "error"], 0),
+        (vec!["-l", "fatal"], 1),
+        (vec!["--grep", "beta"], 0),
+        (vec!["--grep", "gamma"], 1),
+        (vec!["-c", "this.pid == 1"], 1),
+        // Without filters, nothing can fail to match.
+        (vec![], 0),
+    ];
+    for (args, code) in cases {
+        let mut cmd = command();
+        cmd.arg("-q").args(&args).pipe_stdin(&input_path).unwrap();
+        cmd.assert().code(code).stdout("");
+    }
+
+    let mut cmd = command();
+    cmd.arg("--quiet").write_stdin("not a record\n");
+    cmd.assert().code(0).stdout("");
+
+    // Files that cannot be opened take precedence.
+    let mut cmd = command();
+    cmd.args(["-q", "-l", "fatal"])
+        .arg(&input_path)
+        .arg(get_corpus_path().join("does-not-exist.log"));
+    cmd.assert().code(2).stdout("");
+}
+
+#[test]
+fn count_limit() {
+    let input: String = (0..10)
+        .map(|i| {
+            format!(
+                "{{\"name\":\"app\",\"pid\":1,\"hostname\":\"box\",\"level\":{},\"msg\":\"record {}\",\"time\":\"2012-02-08T22:56:52.856Z\",\"v\":0}}\n",
+                if i % 2 == 0 { 30 } else { 40 },
+                i
+            )
+        })
+        .collect();
+
+    let mut cmd = command();
+    cmd.args(["-o", "short", "--no-color", "-n", "3"])
+        .write_stdin(input.clone());
+    cmd.assert().success().stdout(predicates::str::diff(
+        "22:56:52.856  INFO: record 0
+22:56:52.856  WARN: record 1
+22:56:52.856  INFO: record 2
+",
+    ));
+
+    // Only records passing the filters count.
+    let mut cmd = command();
+    cmd.args(["-o", "short", "--no-color", "--count", "3", "-l", "warn"])
+        .write_stdin(input);
+    cmd.assert().success().stdout(predicates::str::diff(
+        "22:56:52.856  WARN: record 1
+22:56:52.856  WARN: record 3
+22:56:52.856  WARN: record 5
+",
+    ));
+
+    // Later files are not read at all.
+    let mut cmd = command();
+    cmd.args(["--no-color", "-n", "1"])
+        .arg(get_corpus_path().join("simple.log"))
+        .arg(get_corpus_path().join("does-not-exist.log"));
+    cmd.assert().success().stdout(predicates::str::diff(
+        "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message\n",
+    ));
+
+    let mut cmd = command();
+    cmd.args(["-n", "0"]).write_stdin("");
+    cmd.assert().failure();
+}
+
+#[test]
+fn level_band() {
+    let input: String = [10, 20, 30, 40, 50, 55, 60]
+        .iter()
+        .map(|level| {
+            format!(
+                "{{\"level\":{},\"msg\":\"at {}\",\"time\":\"2012-02-08T22:56:52.856Z\",\"v\":0}}\n",
+                level, level
+            )
+        })
+        .collect();
+    let band = |args: &[&str]| {
+        let mut cmd = command();
+        cmd.args(["--no-color", "-o", "short"])
+            .args(args)
+            .write_stdin(input.clone());
+        let output = cmd.assert().success().get_output().stdout.clone();
+        String::from_utf8(output).unwrap()
+    };
+
+    // Both bounds are inclusive, and custom levels in between pass.
+    assert_eq!(
+        band(&["-l", "debug", "--level-max", "error"]),
+        "22:56:52.856 DEBUG: at 20
+22:56:52.856  INFO: at 30
+22:56:52.856  WARN: at 40
+22:56:52.856 ERROR: at 50
+"
+    );
+    assert_eq!(
+        band(&["-l", "40", "--level-max", "55"]),
+        "22:56:52.856  WARN: at 40
+22:56:52.856 ERROR: at 50
+22:56:52.856 LVL55: at 55
+"
+    );
+    assert_eq!(
+        band(&["-l", "warn", "--level-max", "warn"]),
+        "22:56:52.856  WARN: at 40\n"
+    );
+    assert_eq!(band(&["-l", "error", "--level-max", "warn"]), "");
+    // With a single bound, the range is open.
+    assert_eq!(
+        band(&["--level-max", "debug"]),
+        "22:56:52.856 TRACE: at 10\n22:56:52.856 DEBUG: at 20\n"
+    );
+
+    let mut cmd = command();
+    cmd.args(["--level-max", "not-a-level"]);
+    cmd.assert().failure().stderr(predicates::str::contains(
+        "Invalid level value: 'not-a-level'",
+    ));
+}
+
+#[test]
+fn context() {
+    let input_path = get_corpus_path().join("context.log");
+
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "short", "-l", "error", "-A1", "-B1"])
+        .arg(&input_path);
+    cmd.assert().success().stdout(predicates::str::diff(
+        "22:56:51.856  INFO: Connecting
+22:56:52.856 ERROR: Connection refused
+22:56:53.856  INFO: Retrying
+not a JSON line
+--
+22:56:56.856  INFO: Stopping
+22:56:57.856 ERROR: Disk full
+",
+    ));
+
+    // Context reaches past lines that are not records, which stay in place.
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "short", "--grep", "Serving", "-B", "2"])
+        .arg(&input_path);
+    cmd.assert().success().stdout(predicates::str::diff(
+        "22:56:53.856  INFO: Retrying
+22:56:54.856  INFO: Connected
+not a JSON line
+22:56:55.856  INFO: Serving
+",
+    ));
+
+    // Reading stops at the `--count`-th match, before its context.
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "short", "-l", "error", "-n", "1"])
+        .args(["-A", "1"])
+        .arg(&input_path);
+    cmd.assert().success().stdout(predicates::str::diff(
+        "22:56:52.856 ERROR: Connection refused\n",
+    ));
+}
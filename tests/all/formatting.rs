@@ -6,7 +6,11 @@ fn simple_log() {
     let input_path = get_corpus_path().join("simple.log");
 
     let mut cmd = command();
-    cmd.arg("--no-color").pipe_stdin(input_path).unwrap();
+    cmd.arg("--no-color")
+        .arg("--time")
+        .arg("utc")
+        .pipe_stdin(input_path)
+        .unwrap();
     cmd.assert().success().stdout(predicate::str::diff(
         "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message\n",
     ));
@@ -17,7 +21,11 @@ fn simple_log_with_color() {
     let input_path = get_corpus_path().join("simple.log");
 
     let mut cmd = command();
-    cmd.arg("--color").pipe_stdin(input_path).unwrap();
+    cmd.arg("--color")
+        .arg("--time")
+        .arg("utc")
+        .pipe_stdin(input_path)
+        .unwrap();
     cmd.assert().success().stdout(predicate::str::diff(
         "[2012-02-08T22:56:52.856Z] \u{1b}[36m INFO\u{1b}[0m: myservice/123 on example.com: \u{1b}[36mMy message\u{1b}[0m\n",
     ));
@@ -104,3 +112,1877 @@ not a JSON line
         .success()
         .stdout(predicate::str::diff(expected));
 }
+
+#[test]
+fn short_output() {
+    let input_path = get_corpus_path().join("extrafield.log");
+
+    let mut cmd = command();
+    cmd.arg("--no-color")
+        .arg("--time")
+        .arg("utc")
+        .arg("-o")
+        .arg("short")
+        .pipe_stdin(input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:52.856  INFO: My message (extra=field)\n",
+    ));
+}
+
+#[test]
+fn json_output_round_trips() {
+    let input_path = get_corpus_path().join("extrafield.log");
+    let input: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&input_path).unwrap()).unwrap();
+
+    for format in &["json", "json-4", "json-0"] {
+        let mut cmd = command();
+        cmd.arg("-o").arg(format).pipe_stdin(&input_path).unwrap();
+        let output = cmd.assert().success().get_output().stdout.clone();
+        let output: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(input, output);
+    }
+}
+
+#[test]
+fn named_level_filter() {
+    let input_path = get_corpus_path().join("all.log");
+
+    let mut numeric = command();
+    numeric
+        .arg("--no-color")
+        .arg("-l")
+        .arg("40")
+        .pipe_stdin(&input_path)
+        .unwrap();
+    let expected = numeric.assert().success().get_output().stdout.clone();
+
+    let mut named = command();
+    named
+        .arg("--no-color")
+        .arg("--level")
+        .arg("WARN")
+        .pipe_stdin(&input_path)
+        .unwrap();
+    named.assert().success().stdout(expected);
+}
+
+#[test]
+fn err_stack_log() {
+    let input_path = get_corpus_path().join("err.log");
+
+    let mut cmd = command();
+    cmd.arg("--no-color").pipe_stdin(input_path).unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z] ERROR: myservice/123 on example.com: My message (err.code=ENOENT)
+    Error: boom
+        at Object.<anonymous> (/app/index.js:1:7)
+        at node:internal/main:1:1
+",
+    ));
+}
+
+#[test]
+fn pino_err_log() {
+    let input_path = get_corpus_path().join("pino-err.log");
+
+    let mut cmd = command();
+    cmd.arg("--no-color").pipe_stdin(input_path).unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z] ERROR: myservice/123 on example.com: My message
+    TypeError: boom
+        at Object.<anonymous> (/app/index.js:1:7)
+        at node:internal/main:1:1
+[2012-02-08T22:56:52.856Z] ERROR: myservice/123 on example.com: My message (err.type=ValidationError)
+    Error: invalid user
+        at validate (/app/user.js:12:11)
+[2012-02-08T22:56:52.856Z] ERROR: myservice/123 on example.com: boom (type=Error)
+    Error: boom
+        at Object.<anonymous> (/app/index.js:1:7)
+",
+    ));
+}
+
+#[test]
+fn req_log() {
+    let input_path = get_corpus_path().join("req.log");
+
+    let mut cmd = command();
+    cmd.arg("--no-color").pipe_stdin(input_path).unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message (req.remoteAddress=10.0.0.1)
+    POST /users HTTP/1.1
+    content-type: text/plain
+    host: example.com
+    \n    hello
+[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message
+    req: {
+      \"url\": \"/users\"
+    }
+",
+    ));
+}
+
+#[test]
+fn pretty_req_body() {
+    let input_path = get_corpus_path().join("json-body.log");
+
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "short", "--pretty-req-body"])
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:52.856  INFO: Created
+    POST /users HTTP/1.1
+    content-type: application/json
+    \n    {
+      \"name\": \"alice\",
+      \"roles\": [
+        \"admin\"
+      ]
+    }
+22:56:53.856  INFO: Listed
+    HTTP/1.1 200 OK
+    content-type: application/json
+    \n    [
+      1,
+      2
+    ]
+",
+    ));
+
+    // Bodies are shown as they are by default.
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "short"])
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::contains(
+        "\n    {\"name\":\"alice\",\"roles\":[\"admin\"]}\n",
+    ));
+
+    // Other bodies are left alone.
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "short", "--pretty-req-body"])
+        .pipe_stdin(get_corpus_path().join("req.log"))
+        .unwrap();
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("    \n    hello\n"));
+}
+
+#[test]
+fn res_log() {
+    let input_path = get_corpus_path().join("res.log");
+
+    let mut cmd = command();
+    cmd.arg("--no-color").pipe_stdin(input_path).unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message
+    HTTP/1.1 404 Not Found
+    content-type: text/plain
+    \n    not here
+",
+    ));
+}
+
+#[test]
+fn res_with_raw_header_log() {
+    let input_path = get_corpus_path().join("res-header.log");
+
+    let mut cmd = command();
+    cmd.arg("--no-color").pipe_stdin(input_path).unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2017-08-02T22:37:34.798Z]  INFO: res-header/76488 on danger0.local: response sent
+    HTTP/1.1 200 OK
+    Foo: bar
+    Date: Wed, 02 Aug 2017 22:37:34 GMT
+    Connection: keep-alive
+    Content-Length: 21
+",
+    ));
+}
+
+#[test]
+fn incomplete_records() {
+    let input_path = get_corpus_path().join("incomplete.log");
+
+    let mut cmd = command();
+    cmd.arg("--no-color").pipe_stdin(&input_path).unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        r#"{"name":"myservice","pid":123,"hostname":"example.com","level":30,"msg":"No time","v":0}
+[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: No level
+[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message
+"#,
+    ));
+
+    // Records without a level are at the default level, at least as far as filters go.
+    let mut cmd = command();
+    cmd.args(["--no-color", "--default-level", "warn", "-l", "warn"])
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        r#"{"name":"myservice","pid":123,"hostname":"example.com","level":30,"msg":"No time","v":0}
+[2012-02-08T22:56:52.856Z]  WARN: myservice/123 on example.com: No level
+"#,
+    ));
+
+    let mut cmd = command();
+    cmd.args(["--no-color", "--default-level", "10", "-o", "json-0"])
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::contains(
+        r#"{"v":0,"level":10,"name":"myservice","hostname":"example.com","pid":123,"time":"2012-02-08T22:56:52.856Z","msg":"No level"}"#,
+    ));
+
+    let mut cmd = command();
+    cmd.arg("--no-color")
+        .arg("--strict")
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message\n",
+    ));
+}
+
+#[test]
+fn no_color_env_var() {
+    let input_path = get_corpus_path().join("simple.log");
+
+    let mut cmd = command();
+    cmd.env("NO_COLOR", "1").pipe_stdin(&input_path).unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message\n",
+    ));
+
+    // An explicit `--color` wins over the environment.
+    let mut cmd = command();
+    cmd.env("NO_COLOR", "1")
+        .arg("--color")
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z] \u{1b}[36m INFO\u{1b}[0m: myservice/123 on example.com: \u{1b}[36mMy message\u{1b}[0m\n",
+    ));
+}
+
+#[test]
+fn no_color_when_piped() {
+    let input_path = get_corpus_path().join("simple.log");
+
+    let mut cmd = command();
+    cmd.env_remove("NO_COLOR").pipe_stdin(input_path).unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message\n",
+    ));
+}
+
+#[test]
+fn time_zones() {
+    let input_path = get_corpus_path().join("simple.log");
+
+    for (args, expected) in &[
+        (
+            &["--time", "local"][..],
+            "[2012-02-08T23:56:52.856+01:00]  INFO: myservice/123 on example.com: My message\n",
+        ),
+        (
+            &[][..],
+            "[2012-02-08T23:56:52.856+01:00]  INFO: myservice/123 on example.com: My message\n",
+        ),
+        (
+            &["--time", "utc"][..],
+            "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message\n",
+        ),
+        (
+            &["-u"][..],
+            "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message\n",
+        ),
+        (
+            &["--utc", "-o", "short"][..],
+            "22:56:52.856  INFO: My message\n",
+        ),
+    ] {
+        let mut cmd = command();
+        cmd.env("TZ", "CET-1")
+            .arg("--no-color")
+            .args(*args)
+            .pipe_stdin(&input_path)
+            .unwrap();
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::diff(*expected));
+    }
+}
+
+#[test]
+fn time_precision() {
+    let input_path = get_corpus_path().join("microseconds.log");
+
+    for (args, expected) in &[
+        (
+            &[][..],
+            "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message\n",
+        ),
+        (
+            &["--time-precision", "us"][..],
+            "[2012-02-08T22:56:52.856123Z]  INFO: myservice/123 on example.com: My message\n",
+        ),
+        (
+            &["--time-precision", "s"][..],
+            "[2012-02-08T22:56:52Z]  INFO: myservice/123 on example.com: My message\n",
+        ),
+        (
+            &["--time-precision", "us", "-o", "short"][..],
+            "22:56:52.856123  INFO: My message\n",
+        ),
+    ] {
+        let mut cmd = command();
+        cmd.arg("--no-color")
+            .arg("--time")
+            .arg("utc")
+            .args(*args)
+            .pipe_stdin(&input_path)
+            .unwrap();
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::diff(*expected));
+    }
+}
+
+#[test]
+fn src_log() {
+    let input_path = get_corpus_path().join("src.log");
+
+    let mut cmd = command();
+    cmd.arg("--no-color").pipe_stdin(input_path).unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com (app.js:42 in handler): My message
+[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com (app.js:42): My message
+[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com (app.js:42): My message
+[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message (src=42)
+",
+    ));
+}
+
+#[test]
+fn bunyan_output() {
+    let input_path = get_corpus_path().join("all.log");
+    let input = std::fs::read_to_string(&input_path).unwrap();
+    let records: Vec<serde_json::Value> = input
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .filter(|record: &serde_json::Value| record.get("level").is_some())
+        .collect();
+
+    let mut cmd = command();
+    cmd.arg("-o").arg("bunyan").pipe_stdin(&input_path).unwrap();
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let output: Vec<serde_json::Value> = String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(records, output);
+}
+
+#[test]
+fn extras_keep_input_order() {
+    let input_path = get_corpus_path().join("ordered-extras.log");
+
+    let mut cmd = command();
+    cmd.arg("--no-color").pipe_stdin(input_path).unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message (zeta=1,alpha=2,mu=3)
+    beta: {
+      \"z\": 1,
+      \"a\": 2
+    }
+",
+    ));
+}
+
+#[test]
+fn details_threshold() {
+    let input_path = get_corpus_path().join("details-threshold.log");
+    let (short, exact, long) = ("a".repeat(49), "b".repeat(50), "c".repeat(51));
+    let cases = [
+        (
+            vec![],
+            format!(
+                "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message (short={},exact={})
+    long: {}
+    --
+    multi: two
+    lines
+",
+                short, exact, long
+            ),
+        ),
+        (
+            vec!["--details-threshold", "49"],
+            format!(
+                "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message (short={})
+    exact: {}
+    --
+    long: {}
+    --
+    multi: two
+    lines
+",
+                short, exact, long
+            ),
+        ),
+        (
+            vec!["--details-threshold", "51"],
+            format!(
+                "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message (short={},exact={},long={})
+    multi: two
+    lines
+",
+                short, exact, long
+            ),
+        ),
+        (
+            vec!["--no-details"],
+            format!(
+                "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message (short={},exact={},long={},multi=\"two\\nlines\")
+",
+                short, exact, long
+            ),
+        ),
+    ];
+
+    for (args, expected) in cases {
+        let mut cmd = command();
+        cmd.arg("--no-color")
+            .args(args)
+            .pipe_stdin(&input_path)
+            .unwrap();
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::diff(expected));
+    }
+}
+
+#[test]
+fn no_details_keeps_special_fields_inline() {
+    let input_path = get_corpus_path().join("err.log");
+
+    let mut cmd = command();
+    cmd.args(["--no-color", "--no-details"])
+        .pipe_stdin(input_path)
+        .unwrap();
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let output = String::from_utf8(output).unwrap();
+    let records = output.lines().filter(|line| line.starts_with('[')).count();
+    assert_eq!(records, output.lines().count());
+}
+
+#[test]
+fn inspect_output() {
+    let input_path = get_corpus_path().join("extrafield.log");
+
+    let mut cmd = command();
+    cmd.args(["-o", "inspect"]).pipe_stdin(input_path).unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        r#"LogRecord {
+    v: Some(
+        0,
+    ),
+    level: 30,
+    name: Some(
+        "myservice",
+    ),
+    hostname: Some(
+        "example.com",
+    ),
+    pid: Some(
+        123,
+    ),
+    time: 2012-02-08T22:56:52.856Z,
+    raw_time: None,
+    message: "My message",
+    extras: {
+        "extra": String("field"),
+    },
+}
+"#,
+    ));
+}
+
+#[test]
+fn msg_color() {
+    let input_path = get_corpus_path().join("simple.log");
+    let cases = [
+        ("cyan", "\u{1b}[36mMy message\u{1b}[0m"),
+        ("green", "\u{1b}[32mMy message\u{1b}[0m"),
+        ("Bright Blue", "\u{1b}[94mMy message\u{1b}[0m"),
+        ("255,128,0", "\u{1b}[38;2;255;128;0mMy message\u{1b}[0m"),
+        ("none", "My message"),
+    ];
+    for (color, expected) in cases {
+        let mut cmd = command();
+        cmd.env("COLORTERM", "truecolor")
+            .args(["--color", "-o", "short", "--msg-color", color])
+            .pipe_stdin(&input_path)
+            .unwrap();
+        cmd.assert().success().stdout(predicate::str::diff(format!(
+            "22:56:52.856 \u{1b}[36m INFO\u{1b}[0m: {}\n",
+            expected
+        )));
+    }
+
+    let mut cmd = command();
+    cmd.args(["--msg-color", "mauve"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid color value: 'mauve'"));
+}
+
+#[test]
+fn durations() {
+    let input_path = get_corpus_path().join("latency.log");
+
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "short"])
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:52.856  INFO: fast (latency=23ms,route=/fast)
+22:56:52.856  INFO: slow (responseTime=1250.5ms)
+22:56:52.856  INFO: unlabeled (duration=23,elapsed=23)
+",
+    ));
+
+    let mut cmd = command();
+    cmd.args(["--color", "-o", "short", "--msg-color", "none"])
+        .args(["--slow-threshold", "100"])
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:52.856 \u{1b}[36m INFO\u{1b}[0m: fast (\u{1b}[1mlatency\u{1b}[0m=23ms,\u{1b}[1mroute\u{1b}[0m=/fast)
+22:56:52.856 \u{1b}[36m INFO\u{1b}[0m: slow (\u{1b}[1mresponseTime\u{1b}[0m=\u{1b}[31m1250.5ms\u{1b}[0m)
+22:56:52.856 \u{1b}[36m INFO\u{1b}[0m: unlabeled (\u{1b}[1mduration\u{1b}[0m=23,\u{1b}[1melapsed\u{1b}[0m=23)
+",
+    ));
+}
+
+#[test]
+fn field_selection() {
+    let input_path = get_corpus_path().join("fields.log");
+    let cases = [
+        (
+            vec![],
+            "22:56:52.856  INFO (app.js:42): My message (req_id=abc,user=alice,attempt=3)\n",
+        ),
+        (
+            vec!["--fields", "attempt,req_id,missing"],
+            "22:56:52.856  INFO: My message (attempt=3,req_id=abc)\n",
+        ),
+        (
+            vec!["--fields", "user", "--fields", "src"],
+            "22:56:52.856  INFO (app.js:42): My message (user=alice)\n",
+        ),
+        (
+            vec!["--exclude-fields", "user,src"],
+            "22:56:52.856  INFO: My message (req_id=abc,attempt=3)\n",
+        ),
+        (
+            vec!["--fields", "user,attempt", "--exclude-fields", "user"],
+            "22:56:52.856  INFO: My message (attempt=3)\n",
+        ),
+    ];
+    for (args, expected) in cases {
+        let mut cmd = command();
+        cmd.args(["--no-color", "-o", "short"])
+            .args(args)
+            .pipe_stdin(&input_path)
+            .unwrap();
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::diff(expected));
+    }
+}
+
+#[test]
+fn show_version() {
+    let input_path = get_corpus_path().join("versions.log");
+
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "short"])
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:52.856  INFO: Current
+22:56:52.856  INFO: Future (extra=field)
+22:56:52.856  INFO: Unversioned
+",
+    ));
+
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "short", "--show-version"])
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:52.856  INFO: Current
+22:56:52.856  INFO: Future (extra=field,v=1)
+22:56:52.856  INFO: Unversioned
+",
+    ));
+}
+
+#[test]
+fn client_req_and_res() {
+    let mut cmd = command();
+    cmd.arg("--no-color")
+        .arg(get_corpus_path().join("client-exchange.log"))
+        .arg(get_corpus_path().join("clientreqres.log"));
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z]  INFO: api/123 on example.com: calling the users service
+    > POST /users HTTP/1.1
+    > content-type: application/json
+    >
+    > {\"name\":\"alice\"}
+[2012-02-08T22:56:52.900Z]  INFO: api/123 on example.com: users service answered
+    < HTTP/1.1 201 Created
+    < location: /users/1
+[2016-02-10T07:28:40.510Z] TRACE: aclientreq/23280 on danger0.local: request sent
+    > GET /--ping HTTP/1.1
+[2016-02-10T07:28:41.419Z] TRACE: aclientreq/23280 on danger0.local: Response received
+    < HTTP/1.1 200 OK
+    < request-id: e8a5a700-cfc7-11e5-a3dc-3b85d20f26ef
+    < content-type: application/json
+",
+    ));
+}
+
+#[test]
+fn scalar_arrays() {
+    let input = r#"{"level":30,"msg":"Hi","time":"2012-02-08T22:56:52.856Z","tags":["a","b","c"],"mixed":[1,"two words",true,null],"nested":[["a"]],"long":["aaaaaaaaaa","bbbbbbbbbb","cccccccccc","dddddddddd","eeeeeeeeee"]}"#;
+
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "short"]).write_stdin(input);
+    cmd.assert().success().stdout(predicate::str::diff(
+        r#"22:56:52.856  INFO: Hi (tags=[a,b,c],mixed=[1,"two words",true,null])
+    nested: [
+      [
+        "a"
+      ]
+    ]
+    --
+    long: [
+      "aaaaaaaaaa",
+      "bbbbbbbbbb",
+      "cccccccccc",
+      "dddddddddd",
+      "eeeeeeeeee"
+    ]
+"#,
+    ));
+}
+
+#[test]
+fn extras_separator_and_wrapping() {
+    let input_path = get_corpus_path().join("fields.log");
+    let cases = [
+        (vec![], " (req_id=abc,user=alice,attempt=3)"),
+        (
+            vec!["--extras-sep", " "],
+            " (req_id=abc user=alice attempt=3)",
+        ),
+        (
+            vec!["--extras-sep", ", "],
+            " (req_id=abc, user=alice, attempt=3)",
+        ),
+        (
+            vec!["--extras-sep", " ", "--extras-wrap", "false"],
+            " req_id=abc user=alice attempt=3",
+        ),
+        (
+            vec!["--extras-wrap", "true"],
+            " (req_id=abc,user=alice,attempt=3)",
+        ),
+    ];
+    for (args, expected) in cases {
+        let mut cmd = command();
+        cmd.args(["--no-color", "-o", "short"])
+            .args(args)
+            .pipe_stdin(&input_path)
+            .unwrap();
+        cmd.assert().success().stdout(predicate::str::diff(format!(
+            "22:56:52.856  INFO (app.js:42): My message{}\n",
+            expected
+        )));
+    }
+}
+
+#[test]
+fn color_values() {
+    let input_path = get_corpus_path().join("typed-extras.log");
+
+    let mut cmd = command();
+    cmd.args([
+        "--color",
+        "-o",
+        "short",
+        "--msg-color",
+        "none",
+        "--color-values",
+    ])
+    .pipe_stdin(&input_path)
+    .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:52.856 \u{1b}[36m INFO\u{1b}[0m: My message (\u{1b}[1muser\u{1b}[0m=\u{1b}[32malice\u{1b}[0m,\u{1b}[1mattempt\u{1b}[0m=\u{1b}[33m3\u{1b}[0m,\u{1b}[1mretry\u{1b}[0m=\u{1b}[32mtrue\u{1b}[0m,\u{1b}[1mparent\u{1b}[0m=\u{1b}[2mnull\u{1b}[0m,\u{1b}[1mtags\u{1b}[0m=[a])
+",
+    ));
+
+    // Values are left alone without colors.
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "short", "--color-values"])
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:52.856  INFO: My message (user=alice,attempt=3,retry=true,parent=null,tags=[a])
+",
+    ));
+}
+
+#[test]
+fn color_themes() {
+    let input_path = get_corpus_path().join("typed-extras.log");
+
+    let cases = vec![
+        (
+            "default",
+            "22:56:52.856 \u{1b}[36m INFO\u{1b}[0m: \u{1b}[36mMy message\u{1b}[0m (\u{1b}[1muser\u{1b}[0m=\u{1b}[32malice\u{1b}[0m,\u{1b}[1mattempt\u{1b}[0m=\u{1b}[33m3\u{1b}[0m,\u{1b}[1mretry\u{1b}[0m=\u{1b}[32mtrue\u{1b}[0m,\u{1b}[1mparent\u{1b}[0m=\u{1b}[2mnull\u{1b}[0m,\u{1b}[1mtags\u{1b}[0m=[a])
+",
+        ),
+        (
+            "solarized",
+            "22:56:52.856 \u{1b}[38;2;38;139;210m INFO\u{1b}[0m: \u{1b}[38;2;42;161;152mMy message\u{1b}[0m (\u{1b}[1muser\u{1b}[0m=\u{1b}[38;2;133;153;0malice\u{1b}[0m,\u{1b}[1mattempt\u{1b}[0m=\u{1b}[38;2;203;75;22m3\u{1b}[0m,\u{1b}[1mretry\u{1b}[0m=\u{1b}[38;2;133;153;0mtrue\u{1b}[0m,\u{1b}[1mparent\u{1b}[0m=\u{1b}[38;2;88;110;117mnull\u{1b}[0m,\u{1b}[1mtags\u{1b}[0m=[a])
+",
+        ),
+        (
+            "mono",
+            "22:56:52.856 \u{1b}[1m INFO\u{1b}[0m: My message (\u{1b}[1muser\u{1b}[0m=alice,\u{1b}[1mattempt\u{1b}[0m=3,\u{1b}[1mretry\u{1b}[0m=true,\u{1b}[1mparent\u{1b}[0m=null,\u{1b}[1mtags\u{1b}[0m=[a])
+",
+        ),
+    ];
+    for (theme, expected) in cases {
+        let mut cmd = command();
+        cmd.args(["--color", "--color-depth", "truecolor", "-o", "short"])
+            .args(["--color-values", "--color-theme", theme])
+            .pipe_stdin(&input_path)
+            .unwrap();
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::diff(expected));
+    }
+
+    // Individual colors take precedence over the theme.
+    let mut cmd = command();
+    cmd.args(["--color", "-o", "short", "--color-theme", "mono"])
+        .args(["--msg-color", "red", "--fields", "user"])
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:52.856 \u{1b}[1m INFO\u{1b}[0m: \u{1b}[31mMy message\u{1b}[0m (\u{1b}[1muser\u{1b}[0m=alice)\n",
+    ));
+
+    let mut cmd = command();
+    cmd.args(["--color-theme", "neon"]).write_stdin("");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "Invalid color theme value: 'neon'",
+    ));
+}
+
+#[test]
+fn json_indent() {
+    let input_path = get_corpus_path().join("nested.log");
+
+    let mut cmd = command();
+    cmd.args(["--no-color", "--json-indent", "4"])
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message
+        user: {
+            \"name\": \"alice\",
+            \"roles\": [
+                \"admin\"
+            ],
+            \"address\": {
+                \"city\": \"Paris\"
+            }
+        }
+        --
+        note: multi
+        line
+",
+    ));
+
+    let mut cmd = command();
+    cmd.arg("--no-color").pipe_stdin(&input_path).unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message
+    user: {
+      \"name\": \"alice\",
+      \"roles\": [
+        \"admin\"
+      ],
+      \"address\": {
+        \"city\": \"Paris\"
+      }
+    }
+    --
+    note: multi
+    line
+",
+    ));
+}
+
+#[test]
+fn logfmt_output() {
+    let input_path = get_corpus_path().join("logfmt.log");
+
+    let mut cmd = command();
+    cmd.args(["-o", "logfmt"]).pipe_stdin(input_path).unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        r#"time=2012-02-08T22:56:52.856Z level=info name=myservice hostname=example.com pid=123 msg="He said \"hi\"" req_id=abc count=3 empty="" path="C:\\tmp" user="{\"id\":1}"
+time=2012-02-08T22:56:52.856Z level=33 name="my service" msg="two\nlines"
+not a record
+"#,
+    ));
+}
+
+#[test]
+fn quote_styles() {
+    let input = r#"{"level":30,"msg":"Hi","time":"2012-02-08T22:56:52.856Z","user":"alice","greeting":"hello world","said":"a \"quote\""}"#;
+    let cases = [
+        (
+            "minimal",
+            r#"22:56:52.856  INFO: Hi (user=alice,greeting="hello world",said="a \"quote\"")"#,
+            r#"time=2012-02-08T22:56:52.856Z level=info msg=Hi user=alice greeting="hello world" said="a \"quote\"""#,
+        ),
+        (
+            "always",
+            r#"22:56:52.856  INFO: Hi (user="alice",greeting="hello world",said="a \"quote\"")"#,
+            r#"time=2012-02-08T22:56:52.856Z level=info msg="Hi" user="alice" greeting="hello world" said="a \"quote\"""#,
+        ),
+        (
+            "never",
+            r#"22:56:52.856  INFO: Hi (user=alice,greeting=hello world,said=a "quote")"#,
+            r#"time=2012-02-08T22:56:52.856Z level=info msg=Hi user=alice greeting=hello world said=a "quote""#,
+        ),
+    ];
+    for (style, short, logfmt) in cases {
+        let mut cmd = command();
+        cmd.args(["--no-color", "-o", "short", "--quote-style", style])
+            .write_stdin(input);
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::diff(format!("{}\n", short)));
+
+        let mut cmd = command();
+        cmd.args(["-o", "logfmt", "--quote-style", style])
+            .write_stdin(input);
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::diff(format!("{}\n", logfmt)));
+    }
+
+    // Newlines are escaped regardless on a single line.
+    let mut cmd = command();
+    cmd.args(["-o", "logfmt", "--quote-style", "never"])
+        .write_stdin(r#"{"level":30,"msg":"two\nlines","time":"2012-02-08T22:56:52.856Z"}"#);
+    cmd.assert().success().stdout(predicate::str::diff(
+        "time=2012-02-08T22:56:52.856Z level=info msg=\"two\\nlines\"\n",
+    ));
+
+    let mut cmd = command();
+    cmd.args(["--quote-style", "sometimes"]).write_stdin("");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "Invalid quote style value: 'sometimes'",
+    ));
+}
+
+#[test]
+fn max_width() {
+    let input_path = get_corpus_path().join("typed-extras.log");
+
+    let cases = vec![
+        (
+            "80",
+            "22:56:52.856  INFO: My message (user=alice,attempt=3,retry=true,parent=null)
+    tags: [a]
+",
+        ),
+        (
+            "60",
+            "22:56:52.856  INFO: My message (user=alice,attempt=3)
+    retry: true
+    --
+    parent: null
+    --
+    tags: [a]
+",
+        ),
+        (
+            "20",
+            "22:56:52.856  INFO: My message
+    user: alice
+    --
+    attempt: 3
+    --
+    retry: true
+    --
+    parent: null
+    --
+    tags: [a]
+",
+        ),
+    ];
+    for (width, expected) in cases {
+        let mut cmd = command();
+        cmd.args(["--no-color", "-o", "short", "--max-width", width])
+            .pipe_stdin(&input_path)
+            .unwrap();
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::diff(expected));
+    }
+
+    // Escape sequences do not count.
+    let mut cmd = command();
+    cmd.args([
+        "--color",
+        "-o",
+        "short",
+        "--max-width",
+        "60",
+        "--fields",
+        "user,attempt,retry",
+    ])
+    .pipe_stdin(&input_path)
+    .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:52.856 \u{1b}[36m INFO\u{1b}[0m: \u{1b}[36mMy message\u{1b}[0m (\u{1b}[1muser\u{1b}[0m=alice,\u{1b}[1mattempt\u{1b}[0m=3)
+    \u{1b}[1mretry\u{1b}[0m: true
+",
+    ));
+
+    // Everything stays inline without details.
+    let mut cmd = command();
+    cmd.args([
+        "--no-color",
+        "-o",
+        "short",
+        "--max-width",
+        "20",
+        "--no-details",
+    ])
+    .pipe_stdin(&input_path)
+    .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:52.856  INFO: My message (user=alice,attempt=3,retry=true,parent=null,tags=[\"a\"])\n",
+    ));
+}
+
+#[test]
+fn highlight_level() {
+    let mut cmd = command();
+    cmd.args(["--color", "--highlight-level"])
+        .pipe_stdin(get_corpus_path().join("err.log"))
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "\u{1b}[31m[2012-02-08T22:56:52.856Z] ERROR: myservice/123 on example.com\u{1b}[0m: \u{1b}[36mMy message\u{1b}[0m (\u{1b}[1merr.code\u{1b}[0m=ENOENT)
+    Error: boom
+        at Object.<anonymous> (/app/index.js:1:7)
+        at node:internal/main:1:1
+",
+    ));
+
+    let mut cmd = command();
+    cmd.args([
+        "--color",
+        "--highlight-level",
+        "-o",
+        "short",
+        "-l",
+        "fatal",
+        "--strict",
+    ])
+    .pipe_stdin(get_corpus_path().join("all.log"))
+    .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "\u{1b}[7m22:56:56.856 FATAL\u{1b}[0m: \u{1b}[36mMy message\u{1b}[0m\n",
+    ));
+}
+
+#[test]
+fn mark_raw() {
+    let input = "not a record
+{\"name\":\"myservice\",\"pid\":123,\"hostname\":\"example.com\",\"level\":30,\"msg\":\"My message\",\"time\":\"2012-02-08T22:56:52.856Z\",\"v\":0}
+{\"msg\":\"not a record either\"}
+";
+    let mut cmd = command();
+    cmd.args(["--no-color", "--mark-raw", "| "])
+        .write_stdin(input);
+    cmd.assert().success().stdout(predicate::str::diff(
+        "| not a record
+[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message
+| {\"msg\":\"not a record either\"}
+",
+    ));
+
+    let mut cmd = command();
+    cmd.args(["--color", "--mark-raw", "> ", "--mark-raw-color", "red"])
+        .write_stdin(input);
+    cmd.assert().success().stdout(predicate::str::diff(
+        "\u{1b}[2;31m> \u{1b}[0mnot a record
+[2012-02-08T22:56:52.856Z] \u{1b}[36m INFO\u{1b}[0m: myservice/123 on example.com: \u{1b}[36mMy message\u{1b}[0m
+\u{1b}[2;31m> \u{1b}[0m{\"msg\":\"not a record either\"}
+",
+    ));
+
+    // Without a marker, lines that are not records are passed through as they are.
+    let mut cmd = command();
+    cmd.arg("--no-color").write_stdin(input);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with("not a record\n"));
+}
+
+#[test]
+fn simple_output() {
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "simple"])
+        .pipe_stdin(get_corpus_path().join("all.log"))
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "# levels
+TRACE: My message
+DEBUG: My message
+ INFO: My message
+ WARN: My message
+ERROR: My message
+LVL55: My message
+FATAL: My message
+
+# extra fields
+ INFO: My message
+ INFO: My message
+ INFO: My message
+ INFO: My message
+ INFO: My message
+ INFO: My message
+
+# bogus
+not a JSON line
+{\"hi\": \"there\"}
+",
+    ));
+
+    let mut cmd = command();
+    cmd.args(["--color", "-o", "simple"])
+        .pipe_stdin(get_corpus_path().join("err.log"))
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "\u{1b}[31mERROR\u{1b}[0m: \u{1b}[36mMy message\u{1b}[0m\n",
+    ));
+}
+
+#[test]
+fn extract() {
+    let extract = |pointer: &str| {
+        let mut cmd = command();
+        cmd.args(["--extract", pointer])
+            .pipe_stdin(get_corpus_path().join("req.log"))
+            .unwrap();
+        let output = cmd.assert().success().get_output().stdout.clone();
+        String::from_utf8(output).unwrap()
+    };
+
+    // Records without the value are skipped.
+    assert_eq!(extract("/req/headers/host"), "example.com\n");
+    assert_eq!(extract("/req/url"), "/users\n/users\n");
+    assert_eq!(
+        extract("/req/headers"),
+        "{\"content-type\":\"text/plain\",\"host\":\"example.com\"}\n"
+    );
+    // Named fields are part of the record too.
+    assert_eq!(extract("/pid"), "123\n123\n");
+    assert_eq!(extract("/req/nope"), "");
+
+    let mut cmd = command();
+    cmd.args(["--extract", "/msg"])
+        .pipe_stdin(get_corpus_path().join("all.log"))
+        .unwrap();
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::diff("My message\n".repeat(13)));
+
+    let mut cmd = command();
+    cmd.args(["--extract", "req"]);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "Invalid JSON pointer value: 'req'",
+    ));
+}
+
+#[test]
+fn truncate_values() {
+    let input = format!(
+        "{{\"name\":\"myservice\",\"pid\":123,\"hostname\":\"example.com\",\"level\":30,\"blob\":\"{}\",\"short\":\"ok\",\"obj\":{{\"a\":[1,2,3]}},\"msg\":\"My message\",\"time\":\"2012-02-08T22:56:52.856Z\",\"v\":0}}\n",
+        "a".repeat(1_000_000)
+    );
+
+    let mut cmd = command();
+    cmd.args(["--no-color", "--truncate-values", "10"])
+        .write_stdin(input.clone());
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message (blob=\"aaaaaaaaaa… (truncated, 1000000 bytes)\",short=ok)
+    obj: {
+      \"a\": [… (truncated, 36 bytes)
+",
+    ));
+
+    let mut cmd = command();
+    cmd.args(["--no-color", "--truncate-values", "10", "--no-details"])
+        .write_stdin(input.clone());
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message (blob=\"aaaaaaaaaa… (truncated, 1000000 bytes)\",short=ok,obj={\"a\":[1,2,… (truncated, 13 bytes))\n",
+    ));
+
+    // Values within the limit are left alone.
+    let mut cmd = command();
+    cmd.args(["--no-color", "--truncate-values", "1000000"])
+        .write_stdin(input);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "    blob: {}\n",
+            "a".repeat(1_000_000)
+        )));
+}
+
+#[test]
+fn flatten() {
+    let input = r#"{"name":"myservice","pid":123,"hostname":"example.com","level":30,"user":{"name":"alice","address":{"city":"Paris","zip":"75001"},"tags":["a","b"],"prefs":{}},"msg":"My message","time":"2012-02-08T22:56:52.856Z","v":0}"#;
+
+    let mut cmd = command();
+    cmd.args(["--no-color", "--flatten"]).write_stdin(input);
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message (user.name=alice,user.address.city=Paris,user.address.zip=75001,user.tags.0=a,user.tags.1=b,user.prefs={})\n",
+    ));
+
+    // Leaves are truncated like any other value.
+    let mut cmd = command();
+    cmd.args([
+        "--no-color",
+        "--flatten",
+        "--truncate-values",
+        "3",
+        "-o",
+        "short",
+    ])
+    .write_stdin(input);
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:52.856  INFO: My message (user.name=\"ali… (truncated, 5 bytes)\",user.address.city=\"Par… (truncated, 5 bytes)\",user.address.zip=\"750… (truncated, 5 bytes)\",user.tags.0=a,user.tags.1=b,user.prefs={})\n",
+    ));
+}
+
+#[test]
+fn origin_toggles() {
+    let origin = |args: &[&str]| {
+        let mut cmd = command();
+        cmd.arg("--no-color")
+            .args(args)
+            .arg(get_corpus_path().join("simple.log"));
+        let output = cmd.assert().success().get_output().stdout.clone();
+        String::from_utf8(output).unwrap()
+    };
+
+    assert_eq!(
+        origin(&["--no-host"]),
+        "[2012-02-08T22:56:52.856Z]  INFO: myservice/123: My message\n"
+    );
+    assert_eq!(
+        origin(&["--no-pid"]),
+        "[2012-02-08T22:56:52.856Z]  INFO: myservice on example.com: My message\n"
+    );
+    assert_eq!(
+        origin(&["--no-name"]),
+        "[2012-02-08T22:56:52.856Z]  INFO: 123 on example.com: My message\n"
+    );
+    assert_eq!(
+        origin(&["--no-host", "--no-pid"]),
+        "[2012-02-08T22:56:52.856Z]  INFO: myservice: My message\n"
+    );
+    assert_eq!(
+        origin(&["--no-name", "--no-pid"]),
+        "[2012-02-08T22:56:52.856Z]  INFO: on example.com: My message\n"
+    );
+    assert_eq!(
+        origin(&["--no-name", "--no-pid", "--no-host"]),
+        "[2012-02-08T22:56:52.856Z]  INFO: My message\n"
+    );
+}
+
+#[test]
+fn custom_time_format() {
+    let mut cmd = command();
+    cmd.args([
+        "--no-color",
+        "--time-format",
+        "%Y-%m-%d %H:%M:%S",
+        "--time-precision",
+        "ns",
+    ])
+    .arg(get_corpus_path().join("simple.log"));
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08 22:56:52]  INFO: myservice/123 on example.com: My message\n",
+    ));
+
+    let mut cmd = command();
+    cmd.env("TZ", "CET-1")
+        .args([
+            "--no-color",
+            "-o",
+            "short",
+            "--time-format",
+            "%d/%m %H:%M%.3f %z",
+        ])
+        .arg(get_corpus_path().join("simple.log"));
+    cmd.assert().success().stdout(predicate::str::diff(
+        "08/02 23:56.856 +0100  INFO: My message\n",
+    ));
+
+    let mut cmd = command();
+    cmd.args(["--time-format", "%Y-%Q"]);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "Invalid time format value: '%Y-%Q'",
+    ));
+}
+
+#[test]
+fn visible_color_escapes() {
+    let mut cmd = command();
+    cmd.args(["--color-escape", "visible", "--color-values"])
+        .pipe_stdin(get_corpus_path().join("err.log"))
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z] <red>ERROR</>: myservice/123 on example.com: <cyan>My message</> (<bold>err.code</>=<green>ENOENT</>)
+    Error: boom
+        at Object.<anonymous> (/app/index.js:1:7)
+        at node:internal/main:1:1
+",
+    ));
+
+    // Colors are shown as specified, whatever the color depth.
+    let mut cmd = command();
+    cmd.args([
+        "--color-escape",
+        "visible",
+        "--color-depth",
+        "16",
+        "--highlight-level",
+        "-o",
+        "short",
+        "-l",
+        "fatal",
+        "--strict",
+    ])
+    .arg(get_corpus_path().join("all.log"));
+    cmd.assert().success().stdout(predicate::str::diff(
+        "<reversed>22:56:56.856 FATAL</>: <cyan>My message</>\n",
+    ));
+
+    let mut cmd = command();
+    cmd.args([
+        "--color-escape",
+        "visible",
+        "--msg-color",
+        "10,20,30",
+        "-o",
+        "simple",
+    ])
+    .arg(get_corpus_path().join("simple.log"));
+    cmd.assert().success().stdout(predicate::str::diff(
+        "<cyan> INFO</>: <10,20,30>My message</>\n",
+    ));
+
+    let mut cmd = command();
+    cmd.args(["--color-escape", "visible", "--no-color", "-o", "simple"])
+        .arg(get_corpus_path().join("simple.log"));
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::diff(" INFO: My message\n"));
+}
+
+#[test]
+fn line_numbers() {
+    let input = "not a record
+{\"name\":\"myservice\",\"pid\":123,\"hostname\":\"example.com\",\"level\":30,\"msg\":\"My message\",\"time\":\"2012-02-08T22:56:52.856Z\",\"v\":0}
+
+{\"name\":\"myservice\",\"pid\":123,\"hostname\":\"example.com\",\"level\":40,\"msg\":\"My message\",\"time\":\"2012-02-08T22:56:53.856Z\",\"v\":0}
+";
+    let mut cmd = command();
+    cmd.args(["--no-color", "-N", "-o", "short"])
+        .write_stdin(input);
+    cmd.assert().success().stdout(predicate::str::diff(
+        "not a record
+2: 22:56:52.856  INFO: My message
+
+4: 22:56:53.856  WARN: My message
+",
+    ));
+
+    // Numbers count skipped lines too, and are dimmed.
+    let mut cmd = command();
+    cmd.args(["--color", "-N", "-o", "short", "-l", "warn"])
+        .write_stdin(input);
+    cmd.assert().success().stdout(predicate::str::diff(
+        "not a record
+
+\u{1b}[2m4: \u{1b}[0m22:56:53.856 \u{1b}[33m WARN\u{1b}[0m: \u{1b}[36mMy message\u{1b}[0m
+",
+    ));
+}
+
+#[test]
+fn output_formats() {
+    let input_path = get_corpus_path().join("extrafield.log");
+    let json = r#"{"v":0,"level":30,"name":"myservice","hostname":"example.com","pid":123,"time":"2012-02-08T22:56:52.856Z","msg":"My message","extra":"field"}
+"#;
+
+    for (format, expected) in [
+        (
+            "long",
+            "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message (extra=field)\n",
+        ),
+        ("short", "22:56:52.856  INFO: My message (extra=field)\n"),
+        (
+            "json",
+            r#"{
+  "v": 0,
+  "level": 30,
+  "name": "myservice",
+  "hostname": "example.com",
+  "pid": 123,
+  "time": "2012-02-08T22:56:52.856Z",
+  "msg": "My message",
+  "extra": "field"
+}
+"#,
+        ),
+        ("json-0", json),
+        ("bunyan", json),
+        (
+            "inspect",
+            r#"LogRecord {
+    v: Some(
+        0,
+    ),
+    level: 30,
+    name: Some(
+        "myservice",
+    ),
+    hostname: Some(
+        "example.com",
+    ),
+    pid: Some(
+        123,
+    ),
+    time: 2012-02-08T22:56:52.856Z,
+    raw_time: None,
+    message: "My message",
+    extras: {
+        "extra": String("field"),
+    },
+}
+"#,
+        ),
+        (
+            "logfmt",
+            "time=2012-02-08T22:56:52.856Z level=info name=myservice hostname=example.com pid=123 msg=\"My message\" extra=field\n",
+        ),
+        ("simple", " INFO: My message\n"),
+        ("table", "2012-02-08T22:56:52.856Z   INFO  123  My message\n"),
+    ] {
+        let mut cmd = command();
+        cmd.args(["--no-color", "--time", "utc", "-o", format])
+            .arg(&input_path);
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::diff(expected));
+    }
+}
+
+#[test]
+fn align() {
+    let input_path = get_corpus_path().join("align.log");
+
+    // Messages line up from the widest prefix on.
+    let mut cmd = command();
+    cmd.args(["--no-color", "--align"]).arg(&input_path);
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z]  INFO: web/1 on a: one
+[2012-02-08T22:56:52.856Z] ERROR: worker/12345 on example.com: two
+[2012-02-08T22:56:52.856Z]  WARN: web/22 on a:                 three
+",
+    ));
+
+    // Escape sequences do not count towards the column.
+    let mut cmd = command();
+    cmd.args(["--color", "--align=50"]).arg(&input_path);
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z] \u{1b}[36m INFO\u{1b}[0m: web/1 on a:     \u{1b}[36mone\u{1b}[0m
+[2012-02-08T22:56:52.856Z] \u{1b}[31mERROR\u{1b}[0m: worker/12345 on example.com: \u{1b}[36mtwo\u{1b}[0m
+[2012-02-08T22:56:52.856Z] \u{1b}[33m WARN\u{1b}[0m: web/22 on a:    \u{1b}[36mthree\u{1b}[0m
+",
+    ));
+
+    let mut cmd = command();
+    cmd.args(["--no-color", "--align=24", "-o", "short"])
+        .arg(&input_path);
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:52.856  INFO:     one
+22:56:52.856 ERROR:     two
+22:56:52.856  WARN:     three
+",
+    ));
+}
+
+#[test]
+fn raw_time() {
+    let input = r#"{"name":"myservice","pid":123,"hostname":"example.com","level":30,"msg":"Offset","time":"2012-02-08T23:56:52.856123+01:00","v":0}
+{"name":"myservice","pid":123,"hostname":"example.com","level":30,"msg":"Seconds","time":"2012-02-08T22:56:52Z","v":0}
+{"name":"myservice","pid":123,"hostname":"example.com","level":30,"msg":"Milliseconds","time":1328741812856,"v":0}
+"#;
+
+    let mut cmd = command();
+    cmd.args(["--no-color", "--raw-time"]).write_stdin(input);
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T23:56:52.856123+01:00]  INFO: myservice/123 on example.com: Offset
+[2012-02-08T22:56:52Z]  INFO: myservice/123 on example.com: Seconds
+[1328741812856]  INFO: myservice/123 on example.com: Milliseconds
+",
+    ));
+
+    // Timestamps are not normalized as JSON numbers either, in any input mode.
+    let numbers = r#"{"name":"myservice","pid":123,"hostname":"example.com","level":30,"msg":"Trailing zeros","time":1328741812.8560,"v":0}
+{"name":"myservice","pid":123,"hostname":"example.com","level":30,"msg":"Exponent","time":1.328741812856E9,"v":0}
+"#;
+    let expected = "[1328741812.8560]  INFO: myservice/123 on example.com: Trailing zeros
+[1.328741812856E9]  INFO: myservice/123 on example.com: Exponent
+";
+    let array = format!("[{}]", numbers.trim_end().replace('\n', ","));
+    for (args, input) in [
+        (vec![], numbers.to_owned()),
+        (vec!["--multiline"], numbers.to_owned()),
+        (vec![], array),
+    ] {
+        let mut cmd = command();
+        cmd.args(["--no-color", "--raw-time"])
+            .args(args)
+            .write_stdin(input);
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::diff(expected));
+    }
+
+    let mut cmd = command();
+    cmd.args(["--no-color", "--raw-time", "-o", "short", "--time", "local"])
+        .env("TZ", "CET-1")
+        .write_stdin(input);
+    cmd.assert().success().stdout(predicate::str::diff(
+        "2012-02-08T23:56:52.856123+01:00  INFO: Offset
+2012-02-08T22:56:52Z  INFO: Seconds
+1328741812856  INFO: Milliseconds
+",
+    ));
+}
+
+#[test]
+fn field_renderers() {
+    let input = r#"{"name":"myservice","pid":123,"hostname":"example.com","level":30,"msg":"My message","time":"2012-02-08T22:56:52.856Z","v":0,"trace_id":"4bf92f35","user":{"id":7},"token":"secret"}
+"#;
+
+    // Trace ids are dimmed out of the box.
+    let mut cmd = command();
+    cmd.args(["--color", "-o", "short"]).write_stdin(input);
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:52.856 \u{1b}[36m INFO\u{1b}[0m: \u{1b}[36mMy message\u{1b}[0m (\u{1b}[1mtrace_id\u{1b}[0m=\u{1b}[2m4bf92f35\u{1b}[0m,\u{1b}[1mtoken\u{1b}[0m=secret)
+    \u{1b}[1muser\u{1b}[0m: {
+      \"id\": 7
+    }
+",
+    ));
+
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "short"])
+        .args(["--render", "token=hidden", "--render", "user=details"])
+        .args(["--details-threshold", "100"])
+        .write_stdin(input);
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:52.856  INFO: My message (trace_id=4bf92f35)
+    user: {
+      \"id\": 7
+    }
+",
+    ));
+
+    let mut cmd = command();
+    cmd.args(["--color", "-o", "short", "--render", "trace_id=red"])
+        .args(["--render", "token=hidden", "--fields", "trace_id,token"])
+        .write_stdin(input);
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:52.856 \u{1b}[36m INFO\u{1b}[0m: \u{1b}[36mMy message\u{1b}[0m (\u{1b}[1mtrace_id\u{1b}[0m=\u{1b}[31m4bf92f35\u{1b}[0m)\n",
+    ));
+
+    // Styled values are quoted and truncated like the other ones, only painted differently.
+    let input = r#"{"name":"myservice","pid":123,"hostname":"example.com","level":30,"msg":"My message","time":"2012-02-08T22:56:52.856Z","v":0,"trace_id":"a b c","span_id":"0123456789","x":"a b"}
+"#;
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "short", "--truncate-values", "4"])
+        .write_stdin(input);
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:52.856  INFO: My message (trace_id=\"a b … (truncated, 5 bytes)\",span_id=\"0123… (truncated, 10 bytes)\",x=\"a b\")\n",
+    ));
+
+    let mut cmd = command();
+    cmd.args(["--render", "token"]).write_stdin(input);
+    cmd.assert()
+        .code(2)
+        .stderr(predicate::str::contains("Invalid render value: 'token'"));
+}
+
+#[test]
+fn boolean_and_null_values() {
+    let input = r#"{"name":"myservice","pid":123,"hostname":"example.com","level":30,"msg":"My message","time":"2012-02-08T22:56:52.856Z","v":0,"cached":true,"retried":false,"parent":null,"attempt":3}
+"#;
+
+    // Booleans and nulls are colored even without `--color-values`.
+    let mut cmd = command();
+    cmd.args(["--color", "-o", "short", "--msg-color", "none"])
+        .write_stdin(input);
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:52.856 \u{1b}[36m INFO\u{1b}[0m: My message (\u{1b}[1mcached\u{1b}[0m=\u{1b}[32mtrue\u{1b}[0m,\u{1b}[1mretried\u{1b}[0m=\u{1b}[31mfalse\u{1b}[0m,\u{1b}[1mparent\u{1b}[0m=\u{1b}[2mnull\u{1b}[0m,\u{1b}[1mattempt\u{1b}[0m=3)\n",
+    ));
+
+    // They are rendered as they are, e.g. never truncated.
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "short", "--truncate-values", "1"])
+        .write_stdin(input);
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:52.856  INFO: My message (cached=true,retried=false,parent=null,attempt=3)\n",
+    ));
+}
+
+#[test]
+fn table() {
+    let input_path = get_corpus_path().join("table.log");
+
+    // Messages are cut at their first line.
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "table"]).arg(&input_path);
+    cmd.assert().success().stdout(predicate::str::diff(
+        "2012-02-08T22:56:52.856Z   INFO  7  Listening
+2012-02-08T22:56:53.856Z  ERROR  12345  A rather long message that will not fit in a narrow terminal
+2012-02-08T22:56:54.856Z   WARN      7  First line…
+",
+    ));
+
+    // Escape sequences do not count towards the width of columns.
+    let mut cmd = command();
+    cmd.args([
+        "--color",
+        "--color-depth",
+        "16",
+        "-o",
+        "table",
+        "--columns",
+        "level,name,msg",
+        "--max-width",
+        "30",
+    ])
+    .arg(&input_path);
+    cmd.assert().success().stdout(predicate::str::diff(
+        "\u{1b}[36m INFO\u{1b}[0m  web  \u{1b}[36mListening\u{1b}[0m
+\u{1b}[31mERROR\u{1b}[0m  worker-pool  \u{1b}[36mA rather \u{2026}\u{1b}[0m
+\u{1b}[33m WARN\u{1b}[0m  web          \u{1b}[36mFirst lin\u{2026}\u{1b}[0m
+",
+    ));
+
+    // Without room for anything of messages, not even an ellipsis, they are left out.
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "table", "--columns", "level,msg"])
+        .args(["--max-width", "7"])
+        .arg(&input_path);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::diff(" INFO\nERROR\n WARN\n"));
+
+    // Extra fields are only shown as columns, empty if records do not have them.
+    let mut cmd = command();
+    cmd.args([
+        "--no-color",
+        "-o",
+        "table",
+        "--columns",
+        "time,extras.user.id,msg,extras.req_id",
+        "--max-width",
+        "60",
+    ])
+    .arg(&input_path);
+    cmd.assert().success().stdout(predicate::str::diff(
+        "2012-02-08T22:56:52.856Z    Listening  a1
+2012-02-08T22:56:53.856Z  7  A rather long message that…
+2012-02-08T22:56:54.856Z     First line…                  b2
+",
+    ));
+
+    let mut cmd = command();
+    cmd.args(["-o", "table", "--columns", "level,extras"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid column value: 'extras'"));
+}
+
+#[test]
+fn records_are_not_separated_by_blank_lines() {
+    let record = |extras: &str| {
+        format!(
+            "{{\"level\":30,\"msg\":\"hi\",\"time\":\"2012-02-08T22:56:52.856Z\"{}}}\n",
+            extras
+        )
+    };
+    let input = [
+        record(""),
+        record(r#","a":"multi\nline""#),
+        record(r#","a":"multi\nline","b":"multi\nline""#),
+        record(r#","short":1"#),
+        record(""),
+    ]
+    .concat();
+
+    // Each record ends with a single newline, whether it has details or not.
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "short"]).write_stdin(input);
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:52.856  INFO: hi
+22:56:52.856  INFO: hi
+    a: multi
+    line
+22:56:52.856  INFO: hi
+    a: multi
+    line
+    --
+    b: multi
+    line
+22:56:52.856  INFO: hi (short=1)
+22:56:52.856  INFO: hi
+",
+    ));
+}
+
+#[test]
+fn relative_times() {
+    let record = |time: &str| {
+        format!(
+            "{{\"level\":30,\"msg\":\"at {}\",\"time\":\"2012-02-08T{}Z\"}}\n",
+            time, time
+        )
+    };
+    let input = [
+        record("22:56:52.856"),
+        record("22:56:54.090"),
+        record("22:58:52.856"),
+        record("22:56:52.356"),
+    ]
+    .concat();
+
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "short", "--time", "relative"])
+        .write_stdin(input.clone());
+    cmd.assert().success().stdout(predicate::str::diff(
+        "+0.000  INFO: at 22:56:52.856
++1.234  INFO: at 22:56:54.090
++120.000  INFO: at 22:58:52.856
+-0.500  INFO: at 22:56:52.356
+",
+    ));
+
+    // Relative to the first record shown.
+    let mut cmd = command();
+    cmd.args([
+        "--no-color",
+        "--time",
+        "relative",
+        "--time-precision",
+        "s",
+        "--grep",
+        "22:5(6:54|8)",
+    ])
+    .write_stdin(input);
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[+0]  INFO: /0 on : at 22:56:54.090
+[+118]  INFO: /0 on : at 22:58:52.856
+",
+    ));
+
+    // Each file starts over, unless they are merged.
+    let web = get_corpus_path().join("merge-web.log");
+    let worker = get_corpus_path().join("merge-worker.log");
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "short", "--time", "relative"])
+        .arg(&web)
+        .arg(&worker);
+    cmd.assert().success().stdout(predicate::str::diff(
+        "+0.000  INFO: request 1
++3.000  INFO: request 2
+stack trace of web
++4.000  INFO: request 3
++0.000  INFO: job 1
++1.000  INFO: job 2
++3.000  INFO: job 3
++5.000  INFO: job 4
+",
+    ));
+
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "short", "--time", "relative", "--merge"])
+        .arg(&web)
+        .arg(&worker);
+    cmd.assert().success().stdout(predicate::str::diff(
+        "+0.000  INFO: request 1
++1.000  INFO: job 1
++2.000  INFO: job 2
++3.000  INFO: request 2
+stack trace of web
++4.000  INFO: request 3
++4.000  INFO: job 3
++6.000  INFO: job 4
+",
+    ));
+
+    let mut cmd = command();
+    cmd.args(["--assume-tz", "relative"]);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "Invalid timezone value: 'relative'",
+    ));
+}
+
+#[test]
+fn group_by() {
+    let input_path = get_corpus_path().join("group-by.log");
+
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "short", "--group-by", "name"])
+        .arg(&input_path);
+    cmd.assert().success().stdout(predicate::str::diff(
+        "name: web
+22:56:52.856  INFO: Listening
+22:56:55.856 ERROR: Failed
+
+name: worker
+22:56:53.856  INFO: Started
+not a record
+22:56:56.856  INFO: Done
+
+(ungrouped)
+22:56:54.856  WARN: Anonymous
+",
+    ));
+
+    // Groups are made of the records that passed the filters.
+    let mut cmd = command();
+    cmd.args([
+        "--no-color",
+        "-o",
+        "short",
+        "--group-by",
+        "pid",
+        "-l",
+        "warn",
+    ])
+    .arg(&input_path);
+    cmd.assert().success().stdout(predicate::str::diff(
+        "not a record
+pid: 9
+22:56:54.856  WARN: Anonymous
+
+pid: 7
+22:56:55.856 ERROR: Failed
+",
+    ));
+
+    // Groups span all the files.
+    let mut cmd = command();
+    cmd.args([
+        "--no-color",
+        "-o",
+        "short",
+        "--group-by",
+        "name",
+        "-l",
+        "error",
+    ])
+    .arg(&input_path)
+    .arg(&input_path);
+    cmd.assert().success().stdout(predicate::str::diff(
+        "not a record
+name: web
+22:56:55.856 ERROR: Failed
+not a record
+22:56:55.856 ERROR: Failed
+",
+    ));
+
+    let mut cmd = command();
+    cmd.args(["--group-by", "req..id"]).arg(&input_path);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "Invalid group by value: 'req..id'",
+    ));
+}
+
+#[test]
+fn output_file() {
+    let input_path = get_corpus_path().join("simple.log");
+    let dir = tempfile::tempdir().unwrap();
+    let output_path = dir.path().join("out.txt");
+    std::fs::write(&output_path, "previous content\n").unwrap();
+
+    // The file is truncated.
+    let mut cmd = command();
+    cmd.args(["-o", "short", "-w"])
+        .arg(&output_path)
+        .arg(&input_path);
+    cmd.assert().success().stdout("");
+    assert_eq!(
+        std::fs::read_to_string(&output_path).unwrap(),
+        "22:56:52.856  INFO: My message\n"
+    );
+
+    // Colors can still be forced.
+    let mut cmd = command();
+    cmd.args(["-o", "short", "--color", "--append", "--output-file"])
+        .arg(&output_path)
+        .arg(&input_path);
+    cmd.assert().success().stdout("");
+    assert_eq!(
+        std::fs::read_to_string(&output_path).unwrap(),
+        "22:56:52.856  INFO: My message
+22:56:52.856 \u{1b}[36m INFO\u{1b}[0m: \u{1b}[36mMy message\u{1b}[0m
+"
+    );
+
+    let mut cmd = command();
+    cmd.args(["-w"])
+        .arg(dir.path().join("missing").join("out.txt"));
+    cmd.arg(&input_path);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to open the output file"));
+}
@@ -2,7 +2,11 @@ use assert_cmd::Command;
 use std::path::PathBuf;
 
 pub fn command() -> Command {
-    Command::cargo_bin("bunyan").unwrap()
+    let mut cmd = Command::cargo_bin("bunyan").unwrap();
+    // Timestamps are displayed in the local timezone by default: pin it to get stable outputs.
+    cmd.env("TZ", "UTC");
+    cmd.env_remove("BUNYAN_OPTS");
+    cmd
 }
 
 pub fn get_corpus_path() -> PathBuf {
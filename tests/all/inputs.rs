@@ -0,0 +1,477 @@
+use crate::helpers::{command, get_corpus_path};
+use predicates::prelude::*;
+
+#[test]
+fn multiple_files_are_concatenated() {
+    let mut cmd = command();
+    cmd.arg("--no-color")
+        .arg(get_corpus_path().join("simple.log"))
+        .arg("-")
+        .arg(get_corpus_path().join("extrafield.log"))
+        .write_stdin("not a JSON line\n");
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message
+not a JSON line
+[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message (extra=field)
+",
+    ));
+}
+
+#[test]
+fn merged_files_are_interleaved_by_time() {
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "short", "--merge"])
+        .arg(get_corpus_path().join("merge-web.log"))
+        .arg(get_corpus_path().join("merge-worker.log"));
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:50.856  INFO: request 1
+22:56:51.856  INFO: job 1
+22:56:52.856  INFO: job 2
+22:56:53.856  INFO: request 2
+stack trace of web
+22:56:54.856  INFO: request 3
+22:56:54.856  INFO: job 3
+22:56:56.856  INFO: job 4
+",
+    ));
+}
+
+#[test]
+fn missing_files_are_reported_and_skipped() {
+    let mut cmd = command();
+    cmd.arg("--no-color")
+        .arg(get_corpus_path().join("does-not-exist.log"))
+        .arg(get_corpus_path().join("simple.log"));
+    cmd.assert()
+        .code(2)
+        .stdout(predicate::str::diff(
+            "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message\n",
+        ))
+        .stderr(predicate::str::contains("does-not-exist.log"));
+}
+
+#[test]
+fn gzipped_inputs_are_decompressed() {
+    let input_path = get_corpus_path().join("simple.log.gz");
+    let expected = "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message\n";
+
+    let mut cmd = command();
+    cmd.arg("--no-color").arg(&input_path);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::diff(expected));
+
+    let mut cmd = command();
+    cmd.arg("--no-color").pipe_stdin(&input_path).unwrap();
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::diff(expected));
+}
+
+#[test]
+fn zstd_and_bzip2_inputs_are_decompressed() {
+    let compressed: &[&str] = &[
+        #[cfg(feature = "zstd")]
+        "simple.log.zst",
+        #[cfg(feature = "bzip2")]
+        "simple.log.bz2",
+    ];
+    let expected = "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message\n";
+    for file_name in compressed {
+        let input_path = get_corpus_path().join(file_name);
+
+        let mut cmd = command();
+        cmd.arg("--no-color").arg(&input_path);
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::diff(expected));
+
+        // Without the extension, the compression format is detected.
+        let mut cmd = command();
+        cmd.arg("--no-color").pipe_stdin(&input_path).unwrap();
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::diff(expected));
+    }
+}
+
+#[test]
+fn text_starting_like_magic_bytes_is_not_compressed() {
+    let mut cmd = command();
+    cmd.arg("--no-color")
+        .write_stdin("BZh is a plain text line\n");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::diff("BZh is a plain text line\n"));
+}
+
+#[test]
+fn json_array_of_records() {
+    let mut cmd = command();
+    cmd.arg("--no-color")
+        .arg(get_corpus_path().join("array.json"))
+        .arg(get_corpus_path().join("simple.log"));
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message
+[2012-02-08T22:56:53.856Z]  WARN: myservice/123 on example.com: Another message (extra=field)
+[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message
+",
+    ));
+}
+
+#[test]
+fn text_starting_with_a_bracket_is_not_an_array() {
+    let mut cmd = command();
+    cmd.arg("--no-color")
+        .write_stdin("[2012-02-08T22:56:52.856Z]  INFO: already formatted\n");
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z]  INFO: already formatted\n",
+    ));
+}
+
+#[test]
+fn lines_starting_like_an_array_are_not_one() {
+    let record = std::fs::read_to_string(get_corpus_path().join("simple.log")).unwrap();
+    let formatted = "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message\n";
+
+    let mut cmd = command();
+    cmd.arg("--no-color")
+        .write_stdin(format!("[] starting\n{}", record));
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::diff(format!("[] starting\n{}", formatted)));
+
+    let mut cmd = command();
+    cmd.arg("--no-color")
+        .write_stdin(format!("[{}]\n{}", record.trim_end(), record));
+    cmd.assert().success().stdout(predicate::str::diff(format!(
+        "[{}]\n{}",
+        record.trim_end(),
+        formatted
+    )));
+}
+
+#[test]
+fn multiline_records() {
+    let mut cmd = command();
+    cmd.arg("--no-color")
+        .pipe_stdin(get_corpus_path().join("simple.log"))
+        .unwrap();
+    let compact = cmd.assert().success().get_output().stdout.clone();
+    let compact = String::from_utf8(compact).unwrap();
+
+    let mut cmd = command();
+    cmd.args(["--no-color", "--multiline"])
+        .pipe_stdin(get_corpus_path().join("multiline.log"))
+        .unwrap();
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::diff(compact.repeat(3)));
+}
+
+#[test]
+fn multiline_records_reject_text() {
+    let mut cmd = command();
+    cmd.args(["--no-color", "--multiline"])
+        .write_stdin("{\"level\": 30,\n\"msg\": \"hi\", \"time\": 0}\nnot JSON\n");
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::diff(
+            "[1970-01-01T00:00:00.000Z]  INFO: /0 on : hi\n",
+        ))
+        .stderr(predicate::str::contains("line 3 column 2"));
+}
+
+#[test]
+fn sorted_records() {
+    let input_path = get_corpus_path().join("unsorted.log");
+
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "short", "--sort"])
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:52.856  INFO: first
+22:56:53.856  INFO: out of order
+22:56:55.856  INFO: late
+",
+    ));
+
+    // Lines that are not records stick to the record they follow.
+    let input = std::fs::read_to_string(&input_path).unwrap();
+    let input = input.replacen('\n', "\nafter first\n", 1);
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "short", "--sort"])
+        .write_stdin(input);
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:52.856  INFO: first
+after first
+22:56:53.856  INFO: out of order
+22:56:55.856  INFO: late
+",
+    ));
+}
+
+#[test]
+fn sort_window() {
+    let record = |second: u32| {
+        format!(
+            "{{\"level\":30,\"msg\":\"{}\",\"time\":\"2012-02-08T22:56:{}Z\"}}\n",
+            second, second
+        )
+    };
+    let input: String = [13, 12, 11, 10]
+        .iter()
+        .map(|second| record(*second))
+        .collect();
+
+    // Records further out of order than the window are written as they come.
+    let mut cmd = command();
+    cmd.args([
+        "-o",
+        "short",
+        "--msg-color",
+        "none",
+        "--sort",
+        "--sort-window",
+        "2",
+    ])
+    .write_stdin(input);
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:11.000  INFO: 11\n22:56:10.000  INFO: 10\n22:56:12.000  INFO: 12\n22:56:13.000  INFO: 13\n",
+    ));
+
+    let mut cmd = command();
+    cmd.args(["--sort-window", "2"]);
+    cmd.assert().failure();
+}
+
+#[test]
+fn stats_summary() {
+    let mut cmd = command();
+    cmd.args(["--no-color", "--stats", "-l", "error"])
+        .arg(get_corpus_path().join("multi.log"))
+        .arg(get_corpus_path().join("custom-levels.log"))
+        .arg("-")
+        .write_stdin("not a record\n{\"not\": \"a record either\"}\n");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::diff(
+            "[2012-02-08T22:56:54.856Z] ERROR: api/789 on beta.example.com: api on beta
+[2012-02-08T22:56:52.856Z] LVL55: myservice/123 on example.com: Unknown
+not a record
+{\"not\": \"a record either\"}
+",
+        ))
+        .stderr(predicate::str::diff(
+            "records: 8
+non-record lines: 2
+trace: 0
+debug: 0
+info: 2
+warn: 1
+error: 1
+fatal: 0
+other: 4
+",
+        ));
+}
+
+#[test]
+fn trailing_partial_line() {
+    let record = std::fs::read_to_string(get_corpus_path().join("simple.log")).unwrap();
+
+    // The last record is formatted even without its newline.
+    let mut cmd = command();
+    cmd.arg("--no-color").write_stdin(record.trim_end());
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message\n",
+    ));
+
+    // A record truncated mid-write, even in the middle of a character, is not a record.
+    let mut input = record.clone().into_bytes();
+    input.extend_from_slice("{\"msg\":\"caf\u{e9}".as_bytes());
+    input.pop();
+    let mut cmd = command();
+    cmd.arg("--no-color").write_stdin(input);
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message
+{\"msg\":\"caf\u{fffd}
+",
+    ));
+
+    let mut cmd = command();
+    cmd.args(["--no-color", "--strict"])
+        .write_stdin(format!("{}{{\"msg\":", record));
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message\n",
+    ));
+}
+
+#[test]
+fn raw_passthrough() {
+    let mut input = std::fs::read(get_corpus_path().join("all.log")).unwrap();
+    input.extend_from_slice(b"not a record\r\n\xff\xfe{\"msg\":");
+
+    let mut cmd = command();
+    cmd.args(["--raw", "-l", "error", "-o", "short", "--color"])
+        .write_stdin(input.clone());
+    let output = cmd.assert().success().get_output().stdout.clone();
+    assert_eq!(output, input);
+}
+
+#[test]
+fn crlf_line_endings() {
+    let expected = "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message
+not a record
+[2012-02-08T22:56:53.856Z]  WARN: myservice/123 on example.com: Other message
+";
+    let mut cmd = command();
+    cmd.arg("--no-color")
+        .arg(get_corpus_path().join("crlf.log"));
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::diff(expected));
+
+    let mut cmd = command();
+    cmd.arg("--no-color")
+        .write_stdin(std::fs::read(get_corpus_path().join("crlf.log")).unwrap());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::diff(expected));
+}
+
+#[test]
+fn byte_order_mark() {
+    let input_path = get_corpus_path().join("bom.log");
+    let expected = "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: After a BOM
+[2012-02-08T22:56:53.856Z]  INFO: myservice/123 on example.com: Second
+";
+
+    let mut cmd = command();
+    cmd.args(["--no-color", "--strict"])
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::diff(expected));
+
+    // Before a JSON array too.
+    let input = std::fs::read_to_string(&input_path).unwrap();
+    let records = input.trim_start_matches('\u{feff}').trim_end();
+    let input = format!("\u{feff}[{}]", records.replace('\n', ","));
+    let mut cmd = command();
+    cmd.arg("--no-color").write_stdin(input);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::diff(expected));
+}
+
+#[test]
+fn leading_garbage() {
+    let input_path = get_corpus_path().join("leading-garbage.log");
+
+    // Only on the first line.
+    let mut cmd = command();
+    cmd.arg("--no-color").pipe_stdin(&input_path).unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: After garbage
+not {\"valid\": json}
+",
+    ));
+
+    let mut cmd = command();
+    cmd.args(["--no-color", "--strict"])
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::is_empty());
+}
+
+#[test]
+fn default_options_from_the_environment() {
+    let input = std::fs::read_to_string(get_corpus_path().join("all.log")).unwrap();
+
+    let mut cmd = command();
+    cmd.env("BUNYAN_OPTS", "--no-color -o short -l warn --strict")
+        .write_stdin(input.clone());
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:53.856  WARN: My message
+22:56:54.856 ERROR: My message
+22:56:55.856 LVL55: My message
+22:56:56.856 FATAL: My message
+",
+    ));
+
+    // Options on the command line take precedence.
+    let mut cmd = command();
+    cmd.env("BUNYAN_OPTS", "--no-color -o short -l warn --strict")
+        .args(["-l", "fatal", "-o", "simple", "--strict"])
+        .write_stdin(input.clone());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::diff("FATAL: My message\n"));
+
+    // Values are split like a shell would.
+    let mut cmd = command();
+    cmd.env(
+        "BUNYAN_OPTS",
+        r#"--no-color --mark-raw '[raw] ' -c "this.msg == \"not\"""#,
+    )
+    .write_stdin("not a record\n");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::diff("[raw] not a record\n"));
+
+    let mut cmd = command();
+    cmd.env("BUNYAN_OPTS", "--mark-raw 'unterminated")
+        .write_stdin(input);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "Invalid BUNYAN_OPTS value: '--mark-raw 'unterminated'",
+    ));
+}
+
+#[test]
+fn command_line_overrides_opposite_defaults() {
+    let input = r#"{"level":30,"msg":"Hi","time":"2012-02-08T22:56:52.856123Z","key":"a value"}"#;
+    let cases = [
+        (
+            "--color",
+            vec!["--no-color"],
+            "22:56:52.856  INFO: Hi (key=\"a value\")\n",
+        ),
+        (
+            "--no-color",
+            vec!["--color"],
+            "22:56:52.856 \u{1b}[36m INFO\u{1b}[0m: \u{1b}[36mHi\u{1b}[0m (\u{1b}[1mkey\u{1b}[0m=\"a value\")\n",
+        ),
+        (
+            "--no-color --details-threshold 5",
+            vec!["--no-details"],
+            "22:56:52.856  INFO: Hi (key=\"a value\")\n",
+        ),
+        (
+            "--no-color --no-details",
+            vec!["--details-threshold", "5"],
+            "22:56:52.856  INFO: Hi\n    key: a value\n",
+        ),
+        (
+            "--no-color --time-precision s",
+            vec!["--raw-time"],
+            "2012-02-08T22:56:52.856123Z  INFO: Hi (key=\"a value\")\n",
+        ),
+        (
+            "--no-color --raw-time",
+            vec!["--time-format", "%H:%M"],
+            "22:56  INFO: Hi (key=\"a value\")\n",
+        ),
+    ];
+    for (defaults, args, expected) in cases {
+        let mut cmd = command();
+        cmd.env("BUNYAN_OPTS", format!("-o short {}", defaults))
+            .args(args)
+            .write_stdin(input);
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::diff(expected));
+    }
+}
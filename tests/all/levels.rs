@@ -1,4 +1,4 @@
-use crate::helpers::command;
+use crate::helpers::{command, get_corpus_path};
 use predicates::prelude::*;
 
 #[test]
@@ -28,3 +28,265 @@ fn valid_levels() {
         cmd.assert().success();
     }
 }
+
+#[test]
+fn custom_levels() {
+    let corpus_path = get_corpus_path();
+
+    let mut cmd = command();
+    // Without it, `colored` approximates r,g,b colors with the closest ANSI color.
+    cmd.env("COLORTERM", "truecolor")
+        .args(["-o", "short", "--color", "--level-map"])
+        .arg(corpus_path.join("level-map.json"))
+        .pipe_stdin(corpus_path.join("custom-levels.log"))
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:52.856 \u{1b}[32mNOTICE\u{1b}[0m: \u{1b}[36mNotice me\u{1b}[0m
+22:56:52.856 \u{1b}[38;2;255;128;0mAUDIT\u{1b}[0m: \u{1b}[36mAudited\u{1b}[0m
+22:56:52.856 ALERT: \u{1b}[36mAlert\u{1b}[0m
+22:56:52.856 LVL55: \u{1b}[36mUnknown\u{1b}[0m
+",
+    ));
+}
+
+#[test]
+fn invalid_level_map() {
+    let mut cmd = command();
+    cmd.arg("--level-map")
+        .arg(get_corpus_path().join("custom-levels.log"));
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid level map"));
+}
+
+#[test]
+fn trace_color_depends_on_color_depth() {
+    let line = r#"{"level":10,"msg":"Tracing","time":"2012-02-08T22:56:52.856Z"}"#;
+    // Arguments, `COLORTERM`, `TERM` and the expected escape sequence.
+    let cases = [
+        (
+            vec!["--color-depth", "truecolor"],
+            "",
+            "xterm",
+            "\u{1b}[38;2;128;128;128m",
+        ),
+        (
+            vec!["--color-depth", "256"],
+            "truecolor",
+            "xterm",
+            "\u{1b}[38;5;244m",
+        ),
+        (
+            vec!["--color-depth", "16"],
+            "truecolor",
+            "xterm",
+            "\u{1b}[90m",
+        ),
+        // Guessed from the environment.
+        (vec![], "24bit", "xterm", "\u{1b}[38;2;128;128;128m"),
+        (vec![], "", "xterm-256color", "\u{1b}[38;5;244m"),
+        (vec![], "", "vt100", "\u{1b}[90m"),
+    ];
+    for (args, colorterm, term, escape) in cases {
+        let mut cmd = command();
+        cmd.env("COLORTERM", colorterm)
+            .env("TERM", term)
+            .args(["--color", "-o", "short", "--msg-color", "none"])
+            .args(args)
+            .write_stdin(line);
+        cmd.assert().success().stdout(predicate::str::diff(format!(
+            "22:56:52.856 {}TRACE\u{1b}[0m: Tracing\n",
+            escape
+        )));
+    }
+}
+
+#[test]
+fn level_width() {
+    let input: String = [5, 25, 30, 50, 100]
+        .iter()
+        .map(|level| {
+            format!(
+                "{{\"level\":{},\"msg\":\"at {}\",\"time\":\"2012-02-08T22:56:52.856Z\"}}\n",
+                level, level
+            )
+        })
+        .collect();
+
+    // Custom and unknown levels are padded like the canonical ones.
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "short", "-l", "0", "--level-map"])
+        .arg(get_corpus_path().join("level-map.json"))
+        .write_stdin(input.clone());
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:52.856  LVL5: at 5
+22:56:52.856 NOTICE: at 25
+22:56:52.856  INFO: at 30
+22:56:52.856 ERROR: at 50
+22:56:52.856 LVL100: at 100
+",
+    ));
+
+    let mut cmd = command();
+    cmd.args([
+        "--no-color",
+        "-o",
+        "short",
+        "--level-width",
+        "6",
+        "-l",
+        "0",
+        "--level-map",
+    ])
+    .arg(get_corpus_path().join("level-map.json"))
+    .write_stdin(input.clone());
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:52.856   LVL5: at 5
+22:56:52.856 NOTICE: at 25
+22:56:52.856   INFO: at 30
+22:56:52.856  ERROR: at 50
+22:56:52.856 LVL100: at 100
+",
+    ));
+
+    let mut cmd = command();
+    cmd.args([
+        "--no-color",
+        "-o",
+        "simple",
+        "--level-width",
+        "0",
+        "-l",
+        "0",
+    ])
+    .write_stdin(input);
+    cmd.assert().success().stdout(predicate::str::diff(
+        "LVL5: at 5
+LVL25: at 25
+INFO: at 30
+ERROR: at 50
+LVL100: at 100
+",
+    ));
+}
+
+#[test]
+fn fatal_style() {
+    let line = r#"{"level":60,"msg":"Boom","time":"2012-02-08T22:56:52.856Z"}"#;
+    let cases = [
+        (vec![], "\u{1b}[7m"),
+        (vec!["--fatal-style", "reversed"], "\u{1b}[7m"),
+        (vec!["--fatal-style", "bold-red"], "\u{1b}[1;31m"),
+        (vec!["--fatal-style", "white-on-red"], "\u{1b}[37;41m"),
+        // Over the style of the theme.
+        (
+            vec![
+                "--color-theme",
+                "solarized",
+                "--fatal-style",
+                "white-on-red",
+            ],
+            "\u{1b}[37;41m",
+        ),
+    ];
+    for (args, escape) in cases {
+        let mut cmd = command();
+        cmd.args(["--color", "-o", "short", "--msg-color", "none"])
+            .args(args)
+            .write_stdin(line);
+        cmd.assert().success().stdout(predicate::str::diff(format!(
+            "22:56:52.856 {}FATAL\u{1b}[0m: Boom\n",
+            escape
+        )));
+    }
+
+    let mut cmd = command();
+    cmd.args(["--fatal-style", "blinking"]).write_stdin(line);
+    cmd.assert().code(2).stderr(predicate::str::contains(
+        "Invalid fatal style value: 'blinking'",
+    ));
+}
+
+#[test]
+fn level_field() {
+    let input_path = get_corpus_path().join("level-fields.log");
+
+    // Records without the field fall back to `level`, while invalid levels are not records.
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "short", "--level-field", "severity"])
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:52.856  WARN: Named severity
+22:56:53.856 ERROR: Numeric severity
+22:56:54.856  INFO: Short name (lvl=debug)
+22:56:55.856  WARN: Plain level
+{\"name\":\"app\",\"pid\":1,\"hostname\":\"box\",\"severity\":\"loud\",\"msg\":\"Not a level\",\"time\":\"2012-02-08T22:56:56.856Z\",\"v\":0}
+",
+    ));
+
+    let mut cmd = command();
+    cmd.args([
+        "--no-color",
+        "-o",
+        "short",
+        "--level-field",
+        "lvl",
+        "--strict",
+        "-l",
+        "debug",
+    ])
+    .pipe_stdin(&input_path)
+    .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:54.856 DEBUG: Short name
+22:56:55.856  WARN: Plain level
+",
+    ));
+}
+
+#[test]
+fn syslog_level_scheme() {
+    let input_path = get_corpus_path().join("syslog-levels.log");
+
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "short", "--level-scheme", "syslog"])
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:52.856 FATAL: Emergency
+22:56:53.856 ERROR: Error
+22:56:54.856  WARN: Warning
+22:56:55.856  INFO: Informational
+22:56:56.856 DEBUG: Debug
+22:56:57.856  WARN: Named level
+",
+    ));
+
+    // Filters are on canonical levels.
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "short", "--level-scheme", "syslog"])
+        .args(["-l", "error"])
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:52.856 FATAL: Emergency
+22:56:53.856 ERROR: Error
+",
+    ));
+
+    // Bunyan levels by default: below trace.
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "short", "-l", "0"])
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::starts_with(
+        "22:56:52.856  LVL0: Emergency\n",
+    ));
+
+    let mut cmd = command();
+    cmd.args(["--level-scheme", "journald"]).write_stdin("");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "Invalid level scheme value: 'journald'",
+    ));
+}
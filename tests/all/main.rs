@@ -1,4 +1,9 @@
+mod conditions;
 mod crashers;
+mod filters;
 mod formatting;
 pub mod helpers;
+mod inputs;
 mod levels;
+mod parsing;
+mod streaming;
@@ -0,0 +1,439 @@
+use crate::helpers::{command, get_corpus_path};
+use predicates::prelude::predicate;
+
+#[test]
+fn timestamp_representations() {
+    let input_path = get_corpus_path().join("timestamps.log");
+
+    let mut cmd = command();
+    cmd.arg("--no-color")
+        .arg("--time-precision")
+        .arg("us")
+        .pipe_stdin(input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856000Z]  INFO: myservice/123 on example.com: ISO 8601
+[2012-02-08T22:56:52.856000Z]  INFO: myservice/123 on example.com: Milliseconds
+[2012-02-08T22:56:52.000000Z]  INFO: myservice/123 on example.com: Seconds
+[2012-02-08T22:56:52.856000Z]  INFO: myservice/123 on example.com: Fractional seconds
+[2012-02-08T22:56:52.856500Z]  INFO: myservice/123 on example.com: Fractional milliseconds
+",
+    ));
+}
+
+#[test]
+fn zoneless_timestamps() {
+    let input_path = get_corpus_path().join("zoneless-timestamps.log");
+
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "short"])
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "22:56:52.856  INFO: Zoned
+22:56:52.856  INFO: Offset
+22:56:52.856  INFO: Zoneless
+22:56:52.000  INFO: Zoneless with a space
+{\"level\":30,\"msg\":\"Not a time\",\"time\":\"yesterday\"}
+",
+    ));
+
+    // UTC+1, see tzset(3).
+    let mut cmd = command();
+    cmd.env("TZ", "CET-1")
+        .args(["--no-color", "--time", "utc", "--assume-tz", "local"])
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z]  INFO: /0 on : Zoned
+[2012-02-08T22:56:52.856Z]  INFO: /0 on : Offset
+[2012-02-08T21:56:52.856Z]  INFO: /0 on : Zoneless
+[2012-02-08T21:56:52.000Z]  INFO: /0 on : Zoneless with a space
+{\"level\":30,\"msg\":\"Not a time\",\"time\":\"yesterday\"}
+",
+    ));
+
+    let mut cmd = command();
+    cmd.args(["--assume-tz", "mars"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid time value: 'mars'"));
+}
+
+#[test]
+fn message_representations() {
+    let input_path = get_corpus_path().join("messages.log");
+
+    let mut cmd = command();
+    cmd.args(["--no-color", "--strict"])
+        .pipe_stdin(input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        r#"[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: A string
+[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: 42
+[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: 1.5
+[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: true
+[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: {"event":"login","user":{"id":7}}
+[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: ["a","b"]
+"#,
+    ));
+}
+
+#[test]
+fn message_fields() {
+    let input_path = get_corpus_path().join("msg-fields.log");
+
+    // `message` stands in for missing `msg`s only.
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "short"])
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        r#"22:56:52.856  INFO: Message field
+22:56:53.856  INFO: Both fields (message=extra)
+{"name":"app","pid":1,"hostname":"box","level":30,"text":"Custom field","time":"2012-02-08T22:56:54.856Z","v":0}
+22:56:55.856  INFO: 
+{"name":"app","pid":1,"hostname":"box","level":30,"time":"2012-02-08T22:56:56.856Z","v":0}
+"#,
+    ));
+
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "short", "--msg-field", "text"])
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        r#"22:56:52.856  INFO: Message field
+22:56:53.856  INFO: Both fields (message=extra)
+22:56:54.856  INFO: Custom field
+22:56:55.856  INFO: 
+{"name":"app","pid":1,"hostname":"box","level":30,"time":"2012-02-08T22:56:56.856Z","v":0}
+"#,
+    ));
+
+    // The message is not repeated among the extra fields.
+    let mut cmd = command();
+    cmd.args(["--msg-field", "text", "-o", "json-0"])
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::contains(
+        r#"{"v":0,"level":30,"name":"app","hostname":"box","pid":1,"time":"2012-02-08T22:56:54.856Z","msg":"Custom field"}"#,
+    ));
+}
+
+#[test]
+fn level_representations() {
+    let input_path = get_corpus_path().join("level-names.log");
+    let unknown = std::fs::read_to_string(&input_path)
+        .unwrap()
+        .lines()
+        .last()
+        .unwrap()
+        .to_owned();
+
+    let mut cmd = command();
+    cmd.arg("--no-color").pipe_stdin(&input_path).unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(format!(
+        "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: Numeric
+[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: Named
+[2012-02-08T22:56:52.856Z]  WARN: myservice/123 on example.com: Upper-case
+{}
+",
+        unknown
+    )));
+
+    // Records with unknown level names are not valid records.
+    let mut cmd = command();
+    cmd.args(["--no-color", "--strict", "-l", "warn"])
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z]  WARN: myservice/123 on example.com: Upper-case\n",
+    ));
+
+    // Named levels are normalized to numbers.
+    let mut cmd = command();
+    cmd.args(["-o", "bunyan"]).pipe_stdin(&input_path).unwrap();
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let levels: Vec<u64> = String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| {
+            serde_json::from_str::<serde_json::Value>(line).unwrap()["level"]
+                .as_u64()
+                .unwrap()
+        })
+        .collect();
+    assert_eq!(levels, [30, 30, 40]);
+}
+
+#[test]
+fn duplicate_keys() {
+    let input_path = get_corpus_path().join("duplicate-keys.log");
+    let duplicate_msg = std::fs::read_to_string(&input_path)
+        .unwrap()
+        .lines()
+        .next()
+        .unwrap()
+        .to_owned();
+
+    // A duplicate field of the record makes it invalid, duplicate extra fields keep their last
+    // value.
+    let mut cmd = command();
+    cmd.arg("--no-color").pipe_stdin(&input_path).unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(format!(
+        "{}
+[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message (x=3,y=2)
+[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message (x=1,y=2)
+",
+        duplicate_msg
+    )));
+
+    let mut cmd = command();
+    cmd.args(["--no-color", "--strict"])
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message (x=1,y=2)\n",
+    ));
+
+    // The same goes for records spanning several lines, or in a JSON array.
+    let records = std::fs::read_to_string(&input_path).unwrap();
+    let array = format!("[{}]", records.trim_end().replace('\n', ",\n"));
+    for (args, input) in [
+        (vec!["--multiline"], records.replace(',', ",\n  ")),
+        (vec![], array),
+    ] {
+        let mut cmd = command();
+        cmd.args(["--no-color", "--strict"])
+            .args(args)
+            .write_stdin(input);
+        cmd.assert().success().stdout(predicate::str::diff(
+            "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message (x=1,y=2)\n",
+        ));
+    }
+}
+
+#[test]
+fn validate() {
+    let mut cmd = command();
+    cmd.arg("--validate")
+        .arg(get_corpus_path().join("invalid.log"));
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::diff(
+            "bunyan: line 3: not a bunyan record\n",
+        ));
+
+    let mut cmd = command();
+    cmd.arg("--validate")
+        .write_stdin("{\"name\":\"myservice\",\"pid\":123,\"hostname\":\"example.com\",\"level\":30,\"msg\":\"My message\",\"time\":\"2012-02-08T22:56:52.856Z\",\"v\":0}\nnot JSON\n");
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::diff("bunyan: line 2: not JSON\n"));
+
+    // Records with duplicate keys are not legal, as in strict mode.
+    let mut cmd = command();
+    cmd.arg("--validate")
+        .arg(get_corpus_path().join("simple.log"))
+        .arg(get_corpus_path().join("duplicate-keys.log"));
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::diff(
+            "bunyan: line 1: not a bunyan record\nbunyan: line 2: not a bunyan record\n",
+        ));
+
+    // Lines are numbered as they are in the input, even when a record spans several of them.
+    let mut cmd = command();
+    cmd.args(["--validate", "--lenient"])
+        .write_stdin("{\"name\":\"myservice\",\"pid\":123,\"hostname\":\"example.com\",\n\"level\":30,\"msg\":\"My message\",\"time\":\"2012-02-08T22:56:52.856Z\",\"v\":0}\nnot JSON\n");
+    cmd.assert()
+        .code(1)
+        .stderr(predicate::str::diff("bunyan: line 3: not JSON\n"));
+
+    let mut cmd = command();
+    cmd.arg("--validate")
+        .arg(get_corpus_path().join("simple.log"));
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::is_empty());
+}
+
+#[test]
+fn gelf_input() {
+    let input_path = get_corpus_path().join("gelf.log");
+
+    let mut cmd = command();
+    cmd.args(["--no-color", "--format", "gelf"])
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z]  INFO: /0 on example.org: A short message (user_id=9001,some_info=foo)
+[2012-02-08T22:56:53.000Z] ERROR: /0 on example.org: Disk is full
+    full_message: Disk is full
+    no space left on /var
+[2012-02-08T22:56:54.500Z] FATAL: /0 on example.org: Without a level
+[2012-02-08T22:56:55.000Z] DEBUG: /0 on example.org: Debugging (facility=app)
+{\"name\":\"myservice\",\"pid\":123,\"hostname\":\"example.com\",\"level\":30,\"msg\":\"A bunyan record\",\"time\":\"2012-02-08T22:56:52.856Z\",\"v\":0}
+",
+    ));
+
+    // GELF messages are not bunyan records.
+    let mut cmd = command();
+    cmd.args(["--no-color", "--strict"])
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: A bunyan record\n",
+    ));
+}
+
+#[test]
+fn pinned_input_formats() {
+    let input_path = get_corpus_path().join("timestamps.log");
+
+    let mut cmd = command();
+    cmd.args(["--no-color", "--input-format", "bunyan"])
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: ISO 8601
+{\"name\":\"myservice\",\"pid\":123,\"hostname\":\"example.com\",\"level\":30,\"msg\":\"Milliseconds\",\"time\":1328741812856,\"v\":0}
+{\"name\":\"myservice\",\"pid\":123,\"hostname\":\"example.com\",\"level\":30,\"msg\":\"Seconds\",\"time\":1328741812,\"v\":0}
+{\"name\":\"myservice\",\"pid\":123,\"hostname\":\"example.com\",\"level\":30,\"msg\":\"Fractional seconds\",\"time\":1328741812.856,\"v\":0}
+{\"name\":\"myservice\",\"pid\":123,\"hostname\":\"example.com\",\"level\":30,\"msg\":\"Fractional milliseconds\",\"time\":1328741812856.5,\"v\":0}
+",
+    ));
+
+    // Integer times are always milliseconds, even small ones.
+    let mut cmd = command();
+    cmd.args(["--no-color", "--input-format", "pino"])
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "{\"name\":\"myservice\",\"pid\":123,\"hostname\":\"example.com\",\"level\":30,\"msg\":\"ISO 8601\",\"time\":\"2012-02-08T22:56:52.856Z\",\"v\":0}
+[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: Milliseconds
+[1970-01-16T09:05:41.812Z]  INFO: myservice/123 on example.com: Seconds
+{\"name\":\"myservice\",\"pid\":123,\"hostname\":\"example.com\",\"level\":30,\"msg\":\"Fractional seconds\",\"time\":1328741812.856,\"v\":0}
+{\"name\":\"myservice\",\"pid\":123,\"hostname\":\"example.com\",\"level\":30,\"msg\":\"Fractional milliseconds\",\"time\":1328741812856.5,\"v\":0}
+",
+    ));
+
+    // Mismatching records are not records, e.g. for --strict.
+    let mut cmd = command();
+    cmd.args(["--no-color", "--strict", "--input-format", "pino"])
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: Milliseconds
+[1970-01-16T09:05:41.812Z]  INFO: myservice/123 on example.com: Seconds
+",
+    ));
+
+    let mut cmd = command();
+    cmd.args(["--input-format", "logstash"]);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "Invalid input format value: 'logstash'",
+    ));
+}
+
+#[test]
+fn lenient_records() {
+    let input_path = get_corpus_path().join("split.log");
+
+    // Lines cut by raw newlines are not JSON.
+    let mut cmd = command();
+    cmd.args(["--no-color", "--strict"])
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "[2012-02-08T22:56:53.856Z]  INFO: myservice/123 on example.com: whole\n",
+    ));
+
+    // Lines that make no record even once joined are passed through.
+    let mut cmd = command();
+    cmd.args(["--no-color", "--lenient", "-N"])
+        .pipe_stdin(&input_path)
+        .unwrap();
+    cmd.assert().success().stdout(predicate::str::diff(
+        "1: [2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: first
+second
+3: [2012-02-08T22:56:53.856Z]  INFO: myservice/123 on example.com: whole
+4: [2012-02-08T22:56:54.856Z] ERROR: myservice/123 on example.com: Boom
+    Error: boom
+        at main (app.js:1:1)
+        at run (app.js:2:2)
+{\"unterminated
+plain text
+",
+    ));
+
+    // Up to 10 lines are joined to the first one.
+    let split = |lines: usize| {
+        format!(
+            "{{\"level\":30,\"msg\":\"{}\",\"time\":\"2012-02-08T22:56:52.856Z\"}}\n",
+            vec!["line"; lines].join("\n")
+        )
+    };
+    let mut cmd = command();
+    cmd.args(["--no-color", "--lenient", "-o", "json-0"])
+        .write_stdin(split(11) + &split(12));
+    let output = cmd.output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        stdout.lines().next(),
+        Some(
+            r#"{"level":30,"time":"2012-02-08T22:56:52.856Z","msg":"line\nline\nline\nline\nline\nline\nline\nline\nline\nline\nline"}"#
+        )
+    );
+    assert_eq!(stdout.lines().count(), 13, "{}", stdout);
+}
+
+#[test]
+fn parse_diagnostics() {
+    let input = r#"{"level":30,"msg":"Hi","time":"2012-02-08T22:56:52.856Z"}
+Starting up
+{"level":30,"msg":"No time"}
+{"level":30,"msg":"cut
+{"level":30,"time":"2012-02-08T22:56:52.856Z"}
+"#;
+
+    // Lines are passed through as usual.
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "short", "--debug-parse"])
+        .write_stdin(input);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::diff(
+            r#"22:56:52.856  INFO: Hi
+Starting up
+{"level":30,"msg":"No time"}
+{"level":30,"msg":"cut
+{"level":30,"time":"2012-02-08T22:56:52.856Z"}
+"#,
+        ))
+        .stderr(predicate::str::diff(
+            "bunyan: line 3: missing field `time` at line 1 column 28
+bunyan: line 4: EOF while parsing a string at line 1 column 22
+bunyan: line 5: missing field `msg`
+",
+        ));
+
+    let mut cmd = command();
+    cmd.args(["--strict", "--no-color", "-o", "short", "--debug-parse"])
+        .write_stdin(r#"{"time":"2012-02-08T22:56:52.856Z","msg":"Hi"}"#);
+    cmd.assert()
+        .success()
+        .stdout("")
+        .stderr(predicate::str::diff(
+            "bunyan: line 1: missing field `level`\n",
+        ));
+
+    // Silent by default.
+    let mut cmd = command();
+    cmd.args(["--no-color", "-o", "short"]).write_stdin(input);
+    cmd.assert().success().stderr("");
+}
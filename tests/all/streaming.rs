@@ -0,0 +1,275 @@
+use crate::helpers::get_corpus_path;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::Duration;
+
+#[test]
+fn records_are_emitted_as_they_arrive() {
+    let record = std::fs::read_to_string(get_corpus_path().join("simple.log")).unwrap();
+
+    let mut child = Command::new(assert_cmd::cargo::cargo_bin("bunyan"))
+        .arg("--no-color")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let stdout = child.stdout.take().unwrap();
+
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            sender.send(line.unwrap()).unwrap();
+        }
+    });
+
+    // Write one record at a time, keeping stdin open: each formatted record must show up
+    // before the next one is written.
+    for _ in 0..3 {
+        stdin.write_all(record.as_bytes()).unwrap();
+        stdin.flush().unwrap();
+        let line = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(line.ends_with("My message"), "{}", line);
+    }
+
+    drop(stdin);
+    assert!(child.wait().unwrap().success());
+}
+
+#[test]
+fn follow_mode_picks_up_appended_and_truncated_content() {
+    let record = std::fs::read_to_string(get_corpus_path().join("simple.log")).unwrap();
+    let extra_record = std::fs::read_to_string(get_corpus_path().join("extrafield.log")).unwrap();
+    let log_file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(log_file.path(), &record).unwrap();
+
+    let mut child = Command::new(assert_cmd::cargo::cargo_bin("bunyan"))
+        .arg("--no-color")
+        .arg("-f")
+        .arg(log_file.path())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let stdout = child.stdout.take().unwrap();
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            sender.send(line.unwrap()).unwrap();
+        }
+    });
+    let next_line = || receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+
+    // Existing content.
+    assert!(next_line().ends_with("My message"));
+
+    // Appended content, written in two chunks: the partial line must not be parsed early.
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(log_file.path())
+        .unwrap();
+    let (head, tail) = extra_record.split_at(20);
+    file.write_all(head.as_bytes()).unwrap();
+    file.flush().unwrap();
+    std::thread::sleep(Duration::from_millis(300));
+    file.write_all(tail.as_bytes()).unwrap();
+    file.flush().unwrap();
+    assert!(next_line().ends_with("My message (extra=field)"));
+
+    // Truncation.
+    std::thread::sleep(Duration::from_millis(300));
+    std::fs::write(log_file.path(), &record).unwrap();
+    assert!(next_line().ends_with("My message"));
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+#[test]
+fn records_are_received_over_tcp() {
+    let record = std::fs::read_to_string(get_corpus_path().join("simple.log")).unwrap();
+
+    let mut child = Command::new(assert_cmd::cargo::cargo_bin("bunyan"))
+        .args(["--no-color", "--listen", "127.0.0.1:0"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let stdout = child.stdout.take().unwrap();
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            sender.send(line.unwrap()).unwrap();
+        }
+    });
+    let next_line = || receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+
+    // Port 0 picks any free port: the actual address is reported on stderr.
+    let mut stderr = BufReader::new(child.stderr.take().unwrap());
+    let mut announcement = String::new();
+    stderr.read_line(&mut announcement).unwrap();
+    let address = announcement
+        .trim()
+        .strip_prefix("bunyan: listening on ")
+        .unwrap()
+        .to_owned();
+
+    // Clients are served one after the other.
+    for _ in 0..2 {
+        let mut client = std::net::TcpStream::connect(&address).unwrap();
+        client.write_all(record.as_bytes()).unwrap();
+        client.write_all(b"not JSON\n").unwrap();
+        drop(client);
+        assert!(next_line().ends_with("My message"));
+        assert_eq!(next_line(), "not JSON");
+    }
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn listening_stops_when_the_output_fails() {
+    let record = std::fs::read_to_string(get_corpus_path().join("simple.log")).unwrap();
+
+    // Writing to `/dev/full` fails as on a full disk: unlike connection errors, it is fatal.
+    let mut child = Command::new(assert_cmd::cargo::cargo_bin("bunyan"))
+        .args(["--no-color", "--listen", "127.0.0.1:0", "-w", "/dev/full"])
+        .env_remove("RUST_BACKTRACE")
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stderr = BufReader::new(child.stderr.take().unwrap());
+    let mut announcement = String::new();
+    stderr.read_line(&mut announcement).unwrap();
+    let address = announcement
+        .trim()
+        .strip_prefix("bunyan: listening on ")
+        .unwrap()
+        .to_owned();
+
+    let mut client = std::net::TcpStream::connect(&address).unwrap();
+    client.write_all(record.as_bytes()).unwrap();
+    drop(client);
+    let mut error = String::new();
+    stderr.read_line(&mut error).unwrap();
+    assert!(error.contains("No space left on device"), "{}", error);
+    assert_eq!(child.wait().unwrap().code(), Some(1));
+}
+
+#[test]
+fn follow_mode_exits_after_count_records() {
+    let mut cmd = assert_cmd::Command::cargo_bin("bunyan").unwrap();
+    cmd.args(["--no-color", "-f", "-n", "1"])
+        .arg(get_corpus_path().join("simple.log"))
+        .timeout(Duration::from_secs(5));
+    cmd.assert().success().stdout(predicates::str::diff(
+        "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message\n",
+    ));
+}
+
+#[test]
+fn closed_output_is_a_clean_exit() {
+    let record = std::fs::read_to_string(get_corpus_path().join("simple.log")).unwrap();
+
+    let mut child = Command::new(assert_cmd::cargo::cargo_bin("bunyan"))
+        .arg("--no-color")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+
+    // Read the first record and hang up, like `head -n 1`.
+    stdin.write_all(record.as_bytes()).unwrap();
+    stdin.flush().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut line = String::new();
+    stdout.read_line(&mut line).unwrap();
+    assert!(line.ends_with("My message\n"), "{}", line);
+    drop(stdout);
+
+    // bunyan might be gone by the time the rest of the input is written.
+    let _ = stdin.write_all(record.as_bytes());
+    drop(stdin);
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+}
+
+#[test]
+fn follow_mode_keeps_following_the_files_that_opened() {
+    let mut cmd = assert_cmd::Command::cargo_bin("bunyan").unwrap();
+    cmd.args(["--no-color", "-f", "-n", "1", "missing.log"])
+        .arg(get_corpus_path().join("simple.log"))
+        .timeout(Duration::from_secs(5));
+    cmd.assert()
+        .code(2)
+        .stdout(predicates::str::diff(
+            "[2012-02-08T22:56:52.856Z]  INFO: myservice/123 on example.com: My message\n",
+        ))
+        .stderr(predicates::str::starts_with("bunyan: missing.log: "));
+}
+
+#[cfg(unix)]
+#[test]
+fn interrupted_follow_mode_flushes_and_summarizes() {
+    let log_file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::copy(get_corpus_path().join("simple.log"), log_file.path()).unwrap();
+
+    let mut child = Command::new(assert_cmd::cargo::cargo_bin("bunyan"))
+        .args(["--no-color", "--stats", "-f"])
+        .arg(log_file.path())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut line = String::new();
+    stdout.read_line(&mut line).unwrap();
+    assert!(line.ends_with("My message\n"), "{}", line);
+
+    let killed = Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(killed.success());
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(130));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.starts_with("records: 1\n"), "{}", stderr);
+}
+
+#[cfg(unix)]
+#[test]
+fn interrupted_quiet_stdin_flushes_and_summarizes() {
+    let record = std::fs::read_to_string(get_corpus_path().join("simple.log")).unwrap();
+
+    let mut child = Command::new(assert_cmd::cargo::cargo_bin("bunyan"))
+        .args(["--no-color", "--stats"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Kept open: reading from stdin blocks until the interruption.
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(record.as_bytes()).unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut line = String::new();
+    stdout.read_line(&mut line).unwrap();
+    assert!(line.ends_with("My message\n"), "{}", line);
+
+    let killed = Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(killed.success());
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(130));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.starts_with("records: 1\n"), "{}", stderr);
+    drop(stdin);
+}